@@ -0,0 +1,12 @@
+-- Store the server region derived from the Genshin UID
+ALTER TABLE config ADD COLUMN region TEXT;
+
+UPDATE config SET region = CASE substr(genshin_uid, length(genshin_uid) - 8, 1)
+    WHEN '1' THEN 'cn_gf01'
+    WHEN '2' THEN 'cn_gf01'
+    WHEN '5' THEN 'cn_gf01'
+    WHEN '6' THEN 'os_usa'
+    WHEN '7' THEN 'os_euro'
+    WHEN '8' THEN 'os_asia'
+    WHEN '9' THEN 'os_cht'
+END;
@@ -0,0 +1,6 @@
+-- Support HoYoLab v2 cookies, which are bound to an account mid
+ALTER TABLE hoyo_cookie ADD COLUMN version INTEGER DEFAULT 1 NOT NULL;
+ALTER TABLE hoyo_cookie ADD COLUMN account_mid TEXT;
+
+-- Every cookie stored so far uses the v1 layout
+UPDATE hoyo_cookie SET version = 1;
@@ -0,0 +1,3 @@
+-- Track whether stored cookies still work
+ALTER TABLE hoyo_cookie ADD COLUMN status TEXT DEFAULT 'unknown' NOT NULL;
+ALTER TABLE hoyo_cookie ADD COLUMN last_verified_at TEXT;
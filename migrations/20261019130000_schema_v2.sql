@@ -0,0 +1,96 @@
+-- Integer Discord ids, one cookie per HoYoLab account, cascading deletes and timestamps on every table
+CREATE TABLE config_v2 (
+    genshin_uid      TEXT    NOT NULL PRIMARY KEY,
+    auto_claim_codes INTEGER DEFAULT 1 NOT NULL,
+    region           TEXT,
+    created_at       TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at       TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE hoyo_cookie_v2 (
+    cookie_id        INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    ltuid            TEXT    NOT NULL UNIQUE,
+    ltoken           TEXT    NOT NULL,
+    cookie_token     TEXT    NOT NULL,
+    account_id       TEXT    NOT NULL,
+    lang             TEXT    NOT NULL,
+    version          INTEGER DEFAULT 1 NOT NULL,
+    account_mid      TEXT,
+    status           TEXT    DEFAULT 'unknown' NOT NULL,
+    last_verified_at TEXT,
+    created_at       TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at       TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE users_v2 (
+    discord_id     INTEGER NOT NULL,
+    hoyo_cookie_id INTEGER NOT NULL,
+    genshin_uid    TEXT    NOT NULL,
+    created_at     TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at     TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (
+        discord_id,
+        genshin_uid
+    ),
+    FOREIGN KEY (
+        genshin_uid
+    )
+    REFERENCES config_v2 (genshin_uid) ON DELETE CASCADE,
+    FOREIGN KEY (
+        hoyo_cookie_id
+    )
+    REFERENCES hoyo_cookie_v2 (cookie_id) ON DELETE CASCADE
+);
+
+CREATE TABLE codes_v2 (
+    code       TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+INSERT INTO config_v2 (genshin_uid, auto_claim_codes, region)
+SELECT genshin_uid, auto_claim_codes, region FROM config;
+
+-- Earlier links stored the same HoYoLab account once per link, keep its newest cookie
+INSERT INTO hoyo_cookie_v2 (cookie_id, ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, status, last_verified_at)
+SELECT cookie_id, ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, status, last_verified_at FROM hoyo_cookie
+WHERE cookie_id IN (SELECT MAX(cookie_id) FROM hoyo_cookie GROUP BY ltuid);
+
+INSERT OR IGNORE INTO users_v2 (discord_id, hoyo_cookie_id, genshin_uid)
+SELECT CAST(trim(users.discord_id) AS INTEGER), newest.cookie_id, users.genshin_uid FROM users
+INNER JOIN hoyo_cookie ON users.hoyo_cookie_id = hoyo_cookie.cookie_id
+INNER JOIN (SELECT MAX(cookie_id) AS cookie_id, ltuid FROM hoyo_cookie GROUP BY ltuid) AS newest ON hoyo_cookie.ltuid = newest.ltuid
+WHERE users.genshin_uid IN (SELECT genshin_uid FROM config_v2);
+
+INSERT INTO codes_v2 (code)
+SELECT code FROM codes;
+
+DROP TABLE users;
+DROP TABLE config;
+DROP TABLE hoyo_cookie;
+DROP TABLE codes;
+
+ALTER TABLE config_v2 RENAME TO config;
+ALTER TABLE hoyo_cookie_v2 RENAME TO hoyo_cookie;
+ALTER TABLE users_v2 RENAME TO users;
+ALTER TABLE codes_v2 RENAME TO codes;
+
+CREATE TRIGGER config_updated_at AFTER UPDATE ON config
+BEGIN
+    UPDATE config SET updated_at = CURRENT_TIMESTAMP WHERE genshin_uid = NEW.genshin_uid;
+END;
+
+CREATE TRIGGER hoyo_cookie_updated_at AFTER UPDATE ON hoyo_cookie
+BEGIN
+    UPDATE hoyo_cookie SET updated_at = CURRENT_TIMESTAMP WHERE cookie_id = NEW.cookie_id;
+END;
+
+CREATE TRIGGER users_updated_at AFTER UPDATE ON users
+BEGIN
+    UPDATE users SET updated_at = CURRENT_TIMESTAMP WHERE (discord_id, genshin_uid) = (NEW.discord_id, NEW.genshin_uid);
+END;
+
+CREATE TRIGGER codes_updated_at AFTER UPDATE ON codes
+BEGIN
+    UPDATE codes SET updated_at = CURRENT_TIMESTAMP WHERE code = NEW.code;
+END;
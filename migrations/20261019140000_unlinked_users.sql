@@ -0,0 +1,21 @@
+-- Unlinked accounts are kept for a grace period, so unlinking can be undone
+CREATE TABLE unlinked_users (
+    batch_id       INTEGER NOT NULL,
+    discord_id     INTEGER NOT NULL,
+    hoyo_cookie_id INTEGER NOT NULL,
+    genshin_uid    TEXT    NOT NULL,
+    linked_at      TEXT    NOT NULL,
+    unlinked_at    TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (
+        batch_id,
+        genshin_uid
+    ),
+    FOREIGN KEY (
+        genshin_uid
+    )
+    REFERENCES config (genshin_uid) ON DELETE CASCADE,
+    FOREIGN KEY (
+        hoyo_cookie_id
+    )
+    REFERENCES hoyo_cookie (cookie_id) ON DELETE CASCADE
+);
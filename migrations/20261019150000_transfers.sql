@@ -0,0 +1,33 @@
+-- Transfers of linked accounts to another Discord user, waiting for the recipient to accept them
+CREATE TABLE transfers (
+    transfer_id     INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    from_discord_id INTEGER NOT NULL,
+    to_discord_id   INTEGER NOT NULL,
+    created_at      TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at      TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE transfer_accounts (
+    transfer_id INTEGER NOT NULL,
+    genshin_uid TEXT    NOT NULL,
+    created_at  TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at  TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (
+        transfer_id,
+        genshin_uid
+    ),
+    FOREIGN KEY (
+        transfer_id
+    )
+    REFERENCES transfers (transfer_id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER transfers_updated_at AFTER UPDATE ON transfers
+BEGIN
+    UPDATE transfers SET updated_at = CURRENT_TIMESTAMP WHERE transfer_id = NEW.transfer_id;
+END;
+
+CREATE TRIGGER transfer_accounts_updated_at AFTER UPDATE ON transfer_accounts
+BEGIN
+    UPDATE transfer_accounts SET updated_at = CURRENT_TIMESTAMP WHERE (transfer_id, genshin_uid) = (NEW.transfer_id, NEW.genshin_uid);
+END;
@@ -0,0 +1,4 @@
+-- Owners control a linked account, viewers only receive its notifications and status
+ALTER TABLE users ADD COLUMN role TEXT DEFAULT 'owner' NOT NULL CHECK (role IN ('owner', 'viewer'));
+
+ALTER TABLE unlinked_users ADD COLUMN role TEXT DEFAULT 'owner' NOT NULL CHECK (role IN ('owner', 'viewer'));
@@ -0,0 +1,6 @@
+-- Names users give their linked accounts, unique per user
+ALTER TABLE users ADD COLUMN alias TEXT;
+
+ALTER TABLE unlinked_users ADD COLUMN alias TEXT;
+
+CREATE UNIQUE INDEX users_alias ON users (discord_id, alias COLLATE NOCASE);
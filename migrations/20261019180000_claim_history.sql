@@ -0,0 +1,18 @@
+-- Outcome of every daily reward and redemption code claim, for statistics
+CREATE TABLE claim_history (
+    claim_id    INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    genshin_uid TEXT    NOT NULL,
+    kind        TEXT    NOT NULL CHECK (kind IN ('daily', 'code')),
+    code        TEXT,
+    outcome     TEXT    NOT NULL,
+    error       TEXT,
+    created_at  TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at  TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX claim_history_genshin_uid ON claim_history (genshin_uid);
+
+CREATE TRIGGER claim_history_updated_at AFTER UPDATE ON claim_history
+BEGIN
+    UPDATE claim_history SET updated_at = CURRENT_TIMESTAMP WHERE claim_id = NEW.claim_id;
+END;
@@ -0,0 +1,2 @@
+-- How often a claim was attempted before it settled on its outcome
+ALTER TABLE claim_history ADD COLUMN attempts INTEGER DEFAULT 1 NOT NULL;
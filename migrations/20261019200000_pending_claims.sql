@@ -0,0 +1,20 @@
+-- Automatic code claims put off while HoYoLab was down
+CREATE TABLE pending_claims (
+    genshin_uid TEXT NOT NULL,
+    code        TEXT NOT NULL,
+    created_at  TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at  TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (
+        genshin_uid,
+        code
+    ),
+    FOREIGN KEY (
+        genshin_uid
+    )
+    REFERENCES config (genshin_uid) ON DELETE CASCADE
+);
+
+CREATE TRIGGER pending_claims_updated_at AFTER UPDATE ON pending_claims
+BEGIN
+    UPDATE pending_claims SET updated_at = CURRENT_TIMESTAMP WHERE (genshin_uid, code) = (NEW.genshin_uid, NEW.code);
+END;
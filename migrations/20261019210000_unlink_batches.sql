@@ -0,0 +1,8 @@
+-- Hands out unlink batch ids, which AUTOINCREMENT never reuses even after a batch was purged
+CREATE TABLE unlink_batches (
+    batch_id   INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT    DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- Continue after the batches that can still be undone
+INSERT INTO unlink_batches (batch_id) SELECT DISTINCT batch_id FROM unlinked_users;
@@ -0,0 +1,150 @@
+use serenity::builder::CreateSelectMenuOption;
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
+use serenity::prelude::Context;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+/// How an account is shown to the user: its alias followed by the UID, or just the UID.
+pub fn name(genshin_uid: &str, alias: Option<&str>) -> String {
+    match alias {
+        Some(alias) => format!("{} ({})", alias, genshin_uid),
+        None => genshin_uid.to_string(),
+    }
+}
+
+/// Names of the user's accounts by their UIDs, in the same order.
+pub async fn names(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+    genshin_uids: &[String],
+) -> Vec<String> {
+    let mut names = Vec::new();
+
+    for genshin_uid in genshin_uids {
+        let alias =
+            sqlx::query("SELECT alias FROM users WHERE (discord_id, genshin_uid) = (?, ?);")
+                .bind(discord_id)
+                .bind(genshin_uid)
+                .fetch_optional(database)
+                .await
+                .unwrap()
+                .and_then(|row| row.get::<Option<String>, _>(0));
+
+        names.push(name(genshin_uid, alias.as_deref()));
+    }
+
+    names
+}
+
+/// Select menu option for a row with the `genshin_uid` and `alias` of an account.
+pub fn option(row: &SqliteRow) -> CreateSelectMenuOption {
+    let genshin_uid = row.get::<String, _>("genshin_uid");
+
+    CreateSelectMenuOption::new(
+        name(
+            &genshin_uid,
+            row.get::<Option<String>, _>("alias").as_deref(),
+        ),
+        genshin_uid,
+    )
+}
+
+/// Suggests the user's linked accounts for the focused option, matching what they typed against
+/// UIDs and aliases. Accounts shared with the user are left out if `owned_only`.
+pub async fn autocomplete(
+    database: &sqlx::SqlitePool,
+    interaction: &AutocompleteInteraction,
+    ctx: &Context,
+    owned_only: bool,
+) {
+    let typed = interaction
+        .data
+        .options
+        .iter()
+        .find(|option| option.focused)
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let accounts = sqlx::query(
+        "SELECT genshin_uid, alias FROM users WHERE discord_id = ? AND (role = 'owner' OR NOT ?);",
+    )
+    .bind(interaction.user.id.0 as i64)
+    .bind(owned_only)
+    .fetch_all(database)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|row| {
+        (
+            row.get::<String, _>("genshin_uid"),
+            row.get::<Option<String>, _>("alias"),
+        )
+    })
+    .filter(|(genshin_uid, alias)| {
+        genshin_uid.contains(&typed)
+            || alias
+                .as_ref()
+                .is_some_and(|alias| alias.to_lowercase().contains(&typed))
+    })
+    .take(25)
+    .collect::<Vec<(String, Option<String>)>>();
+
+    interaction
+        .create_autocomplete_response(&ctx.http, |response| {
+            for (genshin_uid, alias) in &accounts {
+                response.add_string_choice(name(genshin_uid, alias.as_deref()), genshin_uid);
+            }
+
+            response
+        })
+        .await
+        .unwrap();
+}
+
+/// Resolves the optional `account` option of a command, which is `None` if it was left out.
+pub async fn account_option(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+) -> Result<Option<String>, String> {
+    let account = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "account")
+        .and_then(|option| match option.resolved.as_ref() {
+            Some(CommandDataOptionValue::String(account)) => Some(account),
+            _ => None,
+        });
+
+    match account {
+        Some(account) => resolve(database, command.user.id.0 as i64, account)
+            .await
+            .map(Some)
+            .ok_or(format!("You have not linked an account `{}`.", account)),
+        None => Ok(None),
+    }
+}
+
+/// Finds the UID of one of the user's linked accounts by its UID or alias.
+pub async fn resolve(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+    account: &str,
+) -> Option<String> {
+    // Aliases made before numeric ones were refused can still look like a UID, which wins then
+    sqlx::query("SELECT genshin_uid FROM users WHERE discord_id = ? AND (genshin_uid = ? OR alias = ? COLLATE NOCASE) \
+                 ORDER BY genshin_uid = ? DESC;")
+        .bind(discord_id)
+        .bind(account.trim())
+        .bind(account.trim())
+        .bind(account.trim())
+        .fetch_optional(database)
+        .await
+        .unwrap()
+        .map(|row| row.get(0))
+}
@@ -0,0 +1,113 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::prelude::Context;
+use sqlx::Row;
+
+use crate::hoyolab::{self, ClaimOutcome, Credentials};
+use crate::{alias, history};
+
+/// Claims on the account picked with the `account` option, or on all accounts the user owns, and
+/// replies with a line per account. `claim` gets the client, UID and region of each account.
+///
+/// `code` is the redeemed code, or `None` for the daily reward.
+pub async fn each<F, Fut>(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+    code: Option<&str>,
+    claim: F,
+) where
+    F: Fn(hoyolab::Client, String, Option<String>) -> Fut,
+    Fut: Future<Output = (ClaimOutcome, u32)>,
+{
+    // Only the chosen account, or all of them
+    let only = match alias::account_option(database, command).await {
+        Ok(genshin_uid) => genshin_uid,
+        Err(error) => return reply(command, &ctx, &error).await,
+    };
+
+    let users = sqlx::query("SELECT DISTINCT ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, users.genshin_uid, alias, region FROM users \
+                             INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id \
+                             INNER JOIN config on users.genshin_uid = config.genshin_uid \
+                             WHERE discord_id = ? AND role = 'owner' AND (? IS NULL OR users.genshin_uid = ?);")
+        .bind(command.user.id.0 as i64)
+        .bind(&only)
+        .bind(&only)
+        .fetch_all(database)
+        .await
+        .unwrap();
+
+    if users.is_empty() {
+        return reply(command, &ctx, "You do not own any linked accounts").await;
+    }
+
+    if !hoyolab::is_available() {
+        return reply(
+            command,
+            &ctx,
+            "HoYoLab appears to be down, try again later.",
+        )
+        .await;
+    }
+
+    // Retries can take longer than Discord waits for a response
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+        .unwrap();
+
+    let what = match code {
+        Some(code) => format!("code `{}`", code),
+        None => "daily".to_string(),
+    };
+
+    let mut buffer = Vec::<String>::new();
+
+    for user in users {
+        let genshin_uid: String = user.get("genshin_uid");
+        let account = alias::name(
+            &genshin_uid,
+            user.get::<Option<String>, _>("alias").as_deref(),
+        );
+
+        let (outcome, attempts) = claim(
+            hoyolab::Client::new(Credentials::from_row(&user)),
+            genshin_uid.clone(),
+            user.get("region"),
+        )
+        .await;
+
+        // HoYoLab went down halfway, skip the rest instead of listing the same error for each
+        if outcome == ClaimOutcome::Unavailable {
+            buffer.push("HoYoLab appears to be down, skipped the remaining accounts".to_string());
+            break;
+        }
+
+        history::record(database, &genshin_uid, code, &outcome, attempts).await;
+
+        buffer.push(outcome.render(&what, &account));
+    }
+
+    command
+        .edit_original_interaction_response(&ctx.http, |response| {
+            response.content(buffer.join("\n"))
+        })
+        .await
+        .unwrap();
+}
+
+async fn reply(command: &ApplicationCommandInteraction, ctx: &Context, content: &str) {
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(content))
+        })
+        .await
+        .unwrap();
+}
@@ -1,8 +1,6 @@
 use std::sync::Arc;
-use std::time::Duration;
 
 use serenity::builder::CreateApplicationCommand;
-use serenity::futures::StreamExt;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::prelude::component::ButtonStyle;
 use serenity::model::prelude::interaction::InteractionResponseType;
@@ -10,18 +8,16 @@ use serenity::model::Timestamp;
 use serenity::prelude::Context;
 use sqlx::Row;
 
-use crate::commands;
-
 pub async fn run(
     database: &sqlx::SqlitePool,
     command: &ApplicationCommandInteraction,
     ctx: Arc<Context>,
 ) {
-    let discord_id = command.user.id.0.to_string();
+    let discord_id = command.user.id.0;
 
     let users = sqlx::query(
         format!(
-            "SELECT genshin_uid FROM users WHERE discord_id = {}",
+            "SELECT genshin_uid, role, alias FROM users WHERE discord_id = {}",
             discord_id
         )
         .as_str(),
@@ -31,10 +27,19 @@ pub async fn run(
     .unwrap();
 
     let genshin_ids = users
-        .into_iter()
+        .iter()
         .map::<String, _>(|user| user.get(0))
         .collect::<Vec<String>>();
+    let roles = users
+        .iter()
+        .map::<String, _>(|user| user.get(1))
+        .collect::<Vec<String>>();
     let genshin_names = vec!["TBA".to_string(); genshin_ids.len()];
+    let aliases = users
+        .iter()
+        .map::<Option<String>, _>(|user| user.get(2))
+        .map(|alias| alias.unwrap_or("-".to_string()))
+        .collect::<Vec<String>>();
 
     command
         .create_interaction_response(&ctx.http, |response| {
@@ -45,10 +50,12 @@ pub async fn run(
                         e.title("Accounts")
                             .description("**These are your linked accounts:**")
                             .colour((122, 71, 72))
+                            .field("Alias", aliases.join("\n"), true)
                             .field("Genshin Name", genshin_names.join("\n"), true)
                             .field("Genshin UID", genshin_ids.join("\n"), true)
+                            .field("Role", roles.join("\n"), true)
                             .footer(|f| {
-                                f.icon_url(command.user.avatar_url().unwrap()).text(format!(
+                                f.icon_url(command.user.face()).text(format!(
                                     "Requested by {}#{}",
                                     command.user.name, command.user.discriminator
                                 ))
@@ -60,44 +67,34 @@ pub async fn run(
         .await
         .unwrap();
 
-    let msg = command
+    // The buttons are handled by `components::handle`, so they keep working after a restart
+    command
         .user
         .direct_message(&ctx.http, |msg| {
             msg.components(|comp| {
                 comp.create_action_row(|row| {
                     row.create_button(|btn| {
-                        btn.custom_id("link_account_button")
+                        btn.custom_id("accounts:link")
                             .label("Link account")
                             .style(ButtonStyle::Success)
                     })
                     .create_button(|btn| {
-                        btn.custom_id("unlink_account_button")
+                        btn.custom_id("accounts:relink")
+                            .label("Update token")
+                            .style(ButtonStyle::Primary)
+                            .disabled(genshin_ids.is_empty())
+                    })
+                    .create_button(|btn| {
+                        btn.custom_id("accounts:unlink")
                             .label("Unlink account")
                             .style(ButtonStyle::Danger)
-                            .disabled(genshin_ids.len() == 0)
+                            .disabled(genshin_ids.is_empty())
                     })
                 })
             })
         })
         .await
         .unwrap();
-
-    let mut interaction_stream = msg
-        .await_component_interactions(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .build();
-
-    while let Some(interaction) = interaction_stream.next().await {
-        let action = &interaction.data.custom_id;
-
-        msg.delete(&ctx).await.unwrap();
-
-        if action == "link_account_button" {
-            commands::link::link(database, interaction, ctx.clone()).await;
-        } else {
-            commands::unlink::unlink(database, interaction, ctx.clone()).await;
-        }
-    }
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
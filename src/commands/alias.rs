@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::prelude::Context;
+
+use crate::alias;
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    let discord_id = command.user.id.0 as i64;
+
+    let option = |name: &str| {
+        command
+            .data
+            .options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| match option.resolved.as_ref() {
+                Some(CommandDataOptionValue::String(value)) => Some(value.trim().to_string()),
+                _ => None,
+            })
+    };
+
+    let account = option("account").unwrap_or_default();
+    let new_alias = option("alias").filter(|new_alias| !new_alias.is_empty());
+
+    let content = match alias::resolve(database, discord_id, &account).await {
+        _ if new_alias
+            .as_ref()
+            .is_some_and(|new_alias| new_alias.chars().count() > 32) =>
+        {
+            "An alias can be at most 32 characters long.".to_string()
+        }
+        // UIDs are numbers, so a numeric alias could be mistaken for another account's UID
+        _ if new_alias
+            .as_ref()
+            .is_some_and(|new_alias| new_alias.chars().all(|char| char.is_ascii_digit())) =>
+        {
+            "An alias cannot be a number, so it is not mistaken for a UID.".to_string()
+        }
+        None => format!("You have not linked an account `{}`.", account),
+        Some(genshin_uid) => {
+            let query =
+                sqlx::query("UPDATE users SET alias = ? WHERE (discord_id, genshin_uid) = (?, ?);")
+                    .bind(&new_alias)
+                    .bind(discord_id)
+                    .bind(&genshin_uid)
+                    .execute(database)
+                    .await;
+
+            match (query, &new_alias) {
+                (Ok(_), Some(new_alias)) => {
+                    format!("{} is now called `{}`.", genshin_uid, new_alias)
+                }
+                (Ok(_), None) => format!("Removed the alias of {}.", genshin_uid),
+                (Err(sqlx::Error::Database(e)), Some(new_alias))
+                    if e.message().contains("UNIQUE") =>
+                {
+                    format!(
+                        "Another one of your accounts is already called `{}`.",
+                        new_alias
+                    )
+                }
+                (Err(e), _) => format!("Could not change the alias:\n{}", e),
+            }
+        }
+    };
+
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(content))
+        })
+        .await
+        .unwrap();
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("alias")
+        .description("Give one of your linked accounts a name")
+        .create_option(|option| {
+            option
+                .name("account")
+                .description("UID or current alias of the account")
+                .kind(CommandOptionType::String)
+                .required(true)
+                .set_autocomplete(true)
+        })
+        .create_option(|option| {
+            option
+                .name("alias")
+                .description("New alias, leave empty to remove it")
+                .kind(CommandOptionType::String)
+                .required(false)
+        })
+}
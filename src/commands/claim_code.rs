@@ -1,109 +1,47 @@
 use std::sync::Arc;
 
-use hoyo_api::prelude::*;
 use serenity::builder::CreateApplicationCommand;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
-use serenity::model::application::interaction::InteractionResponseType;
 use serenity::model::prelude::command::CommandOptionType;
 use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
 use serenity::prelude::Context;
-use sqlx::Row;
+
+use crate::claim;
+use crate::hoyolab::{self, ClaimOutcome};
 
 pub async fn run(
     database: &sqlx::SqlitePool,
     command: &ApplicationCommandInteraction,
     ctx: Arc<Context>,
 ) {
-    let discord_id = command.user.id.0.to_string();
-
-    let users = sqlx::query(format!("SELECT DISTINCT ltuid, ltoken, cookie_token, account_id, lang, genshin_uid FROM users \
-                                                         INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id \
-                                                         WHERE discord_id = \"{}\";", discord_id).as_str())
-        .fetch_all(database).await.unwrap();
-
-    if users.len() == 0 {
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|msg| msg.content("You have no linked accounts"))
-            })
-            .await
-            .unwrap();
-
-        return;
-    }
-
-    let mut code = String::new();
-
-    if let CommandDataOptionValue::String(c) = command
+    // Options are not guaranteed to arrive in the order they were registered in
+    let code = command
         .data
         .options
-        .get(0)
-        .unwrap()
-        .resolved
-        .as_ref()
-        .unwrap()
-    {
-        code = c.clone();
-    }
-
-    let mut buffer = Vec::<String>::new();
-
-    for user in users {
-        let hoyo_cookie = Cookie::CookieParsed(
-            user.get(0),
-            user.get(1),
-            user.get(2),
-            user.get(3),
-            user.get(4),
-        );
-        let genshin_uid: String = user.get(5);
-
-        let hoyo_client = Client::new(hoyo_cookie, genshin_uid.as_str());
-
-        if let Err(error) = hoyo_client {
-            command
-                .create_interaction_response(&ctx.http, |response| {
-                    response
-                        .kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|msg| {
-                            msg.content(format!("Could not connect hoyo client: {}", error))
-                        })
-                })
-                .await
-                .unwrap();
-
-            return;
-        }
-
-        let hoyo_client = hoyo_client.unwrap();
-        let code = code.clone();
-
-        let output = tokio::task::spawn_blocking(move || {
-            if let Err(error) = hoyo_client.claim_code(&code) {
-                format!(
-                    "Error claiming code `{}` on {}: `{}`",
-                    code, genshin_uid, error
-                )
-            } else {
-                format!("Successfully claimed code `{}` on {}", code, genshin_uid)
-            }
-        })
-        .await
-        .unwrap();
-
-        buffer.push(output);
-    }
-
-    command
-        .create_interaction_response(&ctx.http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|msg| msg.content(buffer.join("\n")))
+        .iter()
+        .find(|option| option.name == "code")
+        .and_then(|option| match option.resolved.as_ref() {
+            Some(CommandDataOptionValue::String(code)) => Some(code.trim().to_string()),
+            _ => None,
         })
-        .await
-        .unwrap();
+        .unwrap_or_default();
+    let code = code.as_str();
+
+    claim::each(
+        database,
+        command,
+        ctx,
+        Some(code),
+        |client, genshin_uid, region| async move {
+            match region {
+                Some(region) => {
+                    hoyolab::with_retry(|| client.claim_code(&genshin_uid, &region, code)).await
+                }
+                None => (ClaimOutcome::Failed("unknown server region".to_string()), 0),
+            }
+        },
+    )
+    .await;
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -117,4 +55,12 @@ pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicatio
                 .kind(CommandOptionType::String)
                 .required(true)
         })
+        .create_option(|option| {
+            option
+                .name("account")
+                .description("UID or alias of the account, all of your accounts if left out")
+                .kind(CommandOptionType::String)
+                .required(false)
+                .set_autocomplete(true)
+        })
 }
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::Timestamp;
+use serenity::prelude::Context;
+use sqlx::Row;
+
+use crate::commands::share;
+use crate::wizard::{Flow, Page, Stop, Transition, Wizard};
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    command
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.embed(|e| {
+                        e.title("Forget me")
+                            .description("Are you sure you want the bot to delete all your data?")
+                            .colour((122, 71, 72))
+                            .footer(|f| {
+                                f.icon_url(command.user.face()).text(format!(
+                                    "Requested by {}#{}",
+                                    command.user.name, command.user.discriminator
+                                ))
+                            })
+                            .timestamp(Timestamp::now())
+                    })
+                })
+        })
+        .await
+        .unwrap();
+
+    Wizard::start(ctx, "forgetme", &command.user, None)
+        .await
+        .run(
+            &mut ForgetFlow {
+                database,
+                discord_id: command.user.id.0 as i64,
+            },
+            (),
+        )
+        .await;
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("forgetme")
+        .description("Delete all data the bot stores about you")
+}
+
+struct ForgetFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    discord_id: i64,
+}
+
+#[async_trait]
+impl Flow for ForgetFlow<'_> {
+    type Step = ();
+
+    async fn step(&mut self, wizard: &mut Wizard, _: ()) -> Result<Transition<()>, Stop> {
+        wizard
+            .ask(
+                Page::new("All your linked accounts, their settings, claim history and HoYoLab cookies no one else uses will be deleted. Accounts you shared stop being shared if you are their last owner. Unlike `/unlink`, this cannot be undone. Proceed?")
+                    .button("proceed", "Forget me", ButtonStyle::Danger),
+            )
+            .await?;
+
+        match forget(self.database, self.discord_id).await {
+            Ok(deleted) => {
+                wizard
+                    .say(format!(
+                        "Deleted:```\n{}```",
+                        deleted
+                            .iter()
+                            .map(|(table, count)| format!("{}: {}", table, count))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    ))
+                    .await
+            }
+            Err(e) => {
+                wizard
+                    .say(format!("Could not delete your data:\n{}", e))
+                    .await
+            }
+        }
+
+        Ok(Transition::Done)
+    }
+}
+
+/// Deletes every row tied to the user in one transaction, returning how many rows were deleted per
+/// table.
+async fn forget(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+) -> Result<Vec<(&'static str, u64)>, sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    // Configs and cookies are shared with others who linked the same accounts
+    let genshin_uids = sqlx::query("SELECT genshin_uid FROM users WHERE discord_id = ? UNION SELECT genshin_uid FROM unlinked_users WHERE discord_id = ?;")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map::<String, _>(|row| row.get(0))
+        .collect::<Vec<String>>();
+
+    let cookie_ids = sqlx::query("SELECT hoyo_cookie_id FROM users WHERE discord_id = ? UNION SELECT hoyo_cookie_id FROM unlinked_users WHERE discord_id = ?;")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map::<u32, _>(|row| row.get(0))
+        .collect::<Vec<u32>>();
+
+    let mut deleted = Vec::new();
+
+    for table in ["users", "unlinked_users"] {
+        let count = sqlx::query(&format!("DELETE FROM {} WHERE discord_id = ?;", table))
+            .bind(discord_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+
+        deleted.push((table, count));
+    }
+
+    let mut viewers = 0;
+
+    for genshin_uid in &genshin_uids {
+        viewers += share::unshare_ownerless(&mut tx, genshin_uid).await?;
+    }
+
+    deleted.push(("shared links", viewers));
+
+    let mut configs = 0;
+
+    for genshin_uid in &genshin_uids {
+        configs += sqlx::query("DELETE FROM config WHERE genshin_uid = ? \
+                                AND NOT EXISTS (SELECT 1 FROM users WHERE genshin_uid = ?) \
+                                AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE genshin_uid = ?);")
+            .bind(genshin_uid)
+            .bind(genshin_uid)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+    }
+
+    deleted.push(("config", configs));
+
+    let mut claims = 0;
+
+    for genshin_uid in &genshin_uids {
+        claims += sqlx::query("DELETE FROM claim_history WHERE genshin_uid = ? \
+                               AND NOT EXISTS (SELECT 1 FROM users WHERE genshin_uid = ?) \
+                               AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE genshin_uid = ?);")
+            .bind(genshin_uid)
+            .bind(genshin_uid)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+    }
+
+    deleted.push(("claim_history", claims));
+
+    let mut cookies = 0;
+
+    for cookie_id in &cookie_ids {
+        cookies += sqlx::query("DELETE FROM hoyo_cookie WHERE cookie_id = ? \
+                                AND NOT EXISTS (SELECT 1 FROM users WHERE hoyo_cookie_id = ?) \
+                                AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE hoyo_cookie_id = ?);")
+            .bind(cookie_id)
+            .bind(cookie_id)
+            .bind(cookie_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+    }
+
+    deleted.push(("hoyo_cookie", cookies));
+
+    let transfers =
+        sqlx::query("DELETE FROM transfers WHERE from_discord_id = ? OR to_discord_id = ?;")
+            .bind(discord_id)
+            .bind(discord_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+
+    deleted.push(("transfers", transfers));
+
+    tx.commit().await?;
+
+    Ok(deleted)
+}
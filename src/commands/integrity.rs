@@ -0,0 +1,81 @@
+use std::env;
+use std::sync::Arc;
+
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::prelude::Context;
+
+use crate::integrity;
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    // Comma-separated Discord ids of the bot's admins
+    let admins = env::var("ADMIN_IDS").unwrap_or_default();
+
+    if !admins
+        .split(',')
+        .any(|admin| admin.trim() == command.user.id.0.to_string())
+    {
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        msg.content("Only admins of the bot may check the database.")
+                    })
+            })
+            .await
+            .unwrap();
+
+        return;
+    }
+
+    let repair = match command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.resolved.as_ref())
+    {
+        Some(CommandDataOptionValue::Boolean(repair)) => *repair,
+        _ => false,
+    };
+
+    let report = integrity::check(database).await;
+
+    let content = if repair && !report.is_clean() {
+        match integrity::repair(database, &report).await {
+            Ok(()) => format!("Repaired:```\n{}```", report),
+            Err(e) => format!("Could not repair the database:\n{}", e),
+        }
+    } else {
+        format!("```\n{}```", report)
+    };
+
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(content))
+        })
+        .await
+        .unwrap();
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("integrity")
+        .description("Check the database for orphaned and duplicate rows (admins only)")
+        .create_option(|option| {
+            option
+                .name("repair")
+                .description("Repair the issues that are found")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
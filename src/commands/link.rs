@@ -1,19 +1,25 @@
 use std::sync::Arc;
-use std::time::Duration;
 
-use hoyo_api::prelude::*;
-use serenity::builder::CreateApplicationCommand;
-use serenity::collector::modal_interaction_collector;
-use serenity::futures::StreamExt;
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommand, CreateSelectMenuOption};
 use serenity::model::application::component::ButtonStyle;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::application::interaction::message_component::MessageComponentInteraction;
-use serenity::model::prelude::component::{ActionRowComponent::InputText, InputTextStyle};
+use serenity::model::prelude::component::InputTextStyle;
 use serenity::model::prelude::interaction::InteractionResponseType;
 use serenity::model::Timestamp;
 use serenity::prelude::Context;
 use sqlx::Row;
 
+use crate::hoyolab::{self, Credentials, GameRole};
+use crate::uid;
+use crate::wizard::{Answer, Flow, Page, Stop, Transition, Wizard};
+
+/// Cookie exports are a few KB, so larger uploads are not downloaded.
+const MAX_COOKIE_FILE_SIZE: u64 = 64 * 1024;
+
+pub const COOKIE_INSTRUCTIONS: &str = "Please login to <https://www.hoyolab.com/>, and write```js\njavascript:document.write(document.cookie)```in the URL bar. Then copy-paste this text into the input field you can open by pressing \"Continue\" below. If your browser blocks this, you can instead export your HoYoLab cookies with a browser extension (JSON) or as a `cookies.txt` file, and paste the export into the input field or upload the file here.";
+
 pub async fn run(
     database: &sqlx::SqlitePool,
     command: &ApplicationCommandInteraction,
@@ -28,7 +34,7 @@ pub async fn run(
                             .description("Are you sure you want to link an account?")
                             .colour((122, 71, 72))
                             .footer(|f| {
-                                f.icon_url(command.user.avatar_url().unwrap()).text(format!(
+                                f.icon_url(command.user.face()).text(format!(
                                     "Requested by {}#{}",
                                     command.user.name, command.user.discriminator
                                 ))
@@ -40,41 +46,13 @@ pub async fn run(
         .await
         .unwrap();
 
-    let msg = command
-        .user
-        .direct_message(&ctx.http, |msg| {
-            msg.components(|comp| {
-                comp.create_action_row(|row| {
-                    row.create_button(|btn| {
-                        btn.custom_id("cancel")
-                            .label("Cancel")
-                            .style(ButtonStyle::Secondary)
-                    })
-                    .create_button(|btn| {
-                        btn.custom_id("link_account")
-                            .label("Link")
-                            .style(ButtonStyle::Success)
-                    })
-                })
-            })
-        })
+    Wizard::start(ctx, "link", &command.user, None)
         .await
-        .unwrap();
-
-    let mut interaction_stream = msg
-        .await_component_interactions(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .build();
-
-    while let Some(interaction) = interaction_stream.next().await {
-        let action = &interaction.data.custom_id;
-
-        msg.delete(&ctx).await.unwrap();
-
-        if action == "link_account" {
-            link(database, interaction, ctx.clone()).await;
-        }
-    }
+        .run(
+            &mut LinkFlow::new(database, command.user.id.0 as i64),
+            LinkStep::Start,
+        )
+        .await;
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -88,244 +66,297 @@ pub async fn link(
     interaction: Arc<MessageComponentInteraction>,
     ctx: Arc<Context>,
 ) {
-    let discord_id = interaction.user.id.0.to_string();
-
-    interaction.create_interaction_response(&ctx, |res| {
-        res.kind(InteractionResponseType::ChannelMessageWithSource)
-            .interaction_response_data(|msg| {
-                msg.embed(|e| {
-                    e.description("Please login to <https://www.hoyolab.com/>, and write```js\njavascript:document.write(document.cookie)```in the URL bar. Then copy-paste this text into the input field you can open by pressing \"Continue\" below. Furthermore, you must type your in-game UID into the designated text input field.")
-                        .colour((122, 71, 72))
-                })
-            })
-    }).await.unwrap();
-
-    let msg = interaction
-        .user
-        .direct_message(&ctx.http, |msg| {
-            msg.components(|comp| {
-                comp.create_action_row(|row| {
-                    row.create_button(|btn| {
-                        btn.custom_id("cancel")
-                            .label("Cancel")
-                            .style(ButtonStyle::Secondary)
-                    })
-                    .create_button(|btn| {
-                        btn.custom_id("proceed")
-                            .label("Continue")
-                            .style(ButtonStyle::Success)
-                    })
-                })
-            })
-        })
+    let discord_id = interaction.user.id.0 as i64;
+    let user = interaction.user.clone();
+
+    Wizard::start(ctx, "link", &user, Some(interaction))
         .await
-        .unwrap();
+        .run(&mut LinkFlow::new(database, discord_id), LinkStep::Cookie)
+        .await;
+}
 
-    let mut interaction_stream = msg
-        .await_component_interactions(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .build();
-
-    while let Some(interaction) = interaction_stream.next().await {
-        let action = &interaction.data.custom_id;
-
-        msg.delete(&ctx).await.unwrap();
-
-        if action == "proceed" {
-            interaction
-                .create_interaction_response(&ctx, |res| {
-                    res.kind(InteractionResponseType::Modal)
-                        .interaction_response_data(|msg| {
-                            msg.title("Link form")
-                                .custom_id("link_form")
-                                .components(|comp| {
-                                    comp.create_action_row(|row| {
-                                        row.create_input_text(|input| {
-                                            input
-                                                .custom_id("genshin_uid")
-                                                .label("Genshin UID:")
-                                                .placeholder("123456789")
-                                                .style(InputTextStyle::Short)
-                                        })
-                                    })
-                                    .create_action_row(
-                                        |row| {
-                                            row.create_input_text(|input| {
-                                                input
-                                                    .custom_id("cookie")
-                                                    .label("HoYoLab Token:")
-                                                    .style(InputTextStyle::Paragraph)
-                                            })
-                                        },
-                                    )
-                                })
-                        })
-                })
+#[derive(Clone)]
+enum LinkStep {
+    Start,
+    Cookie,
+    /// The roles that can be linked, and the lines listing those skipped for their UID
+    Select(Credentials, Vec<GameRole>, Vec<String>),
+    Confirm(Credentials, Vec<GameRole>),
+}
+
+struct LinkFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    discord_id: i64,
+}
+
+impl<'a> LinkFlow<'a> {
+    fn new(database: &'a sqlx::SqlitePool, discord_id: i64) -> LinkFlow<'a> {
+        LinkFlow {
+            database,
+            discord_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Flow for LinkFlow<'_> {
+    type Step = LinkStep;
+
+    async fn step(
+        &mut self,
+        wizard: &mut Wizard,
+        step: LinkStep,
+    ) -> Result<Transition<LinkStep>, Stop> {
+        match step {
+            LinkStep::Start => {
+                wizard
+                    .ask(Page::default().button("proceed", "Link", ButtonStyle::Success))
+                    .await?;
+
+                Ok(Transition::Next(LinkStep::Cookie))
+            }
+            LinkStep::Cookie => {
+                let instructions = format!("{} You can then pick which of the Genshin accounts on your HoYoLab account you want to link.", COOKIE_INSTRUCTIONS);
+
+                let hoyo_cookie = match ask_cookie(wizard, instructions).await? {
+                    Some(hoyo_cookie) => hoyo_cookie,
+                    None => return Ok(Transition::Stay(LinkStep::Cookie)),
+                };
+
+                let (credentials, roles) = match verify(&hoyo_cookie).await {
+                    Ok(val) => val,
+                    Err(error) => {
+                        wizard.say(format!("{}.\nPlease try again.", error)).await;
+
+                        return Ok(Transition::Stay(LinkStep::Cookie));
+                    }
+                };
+
+                // Accounts shared with the user can be linked to become their owner
+                let linked = sqlx::query(
+                    "SELECT genshin_uid FROM users WHERE discord_id = ? AND role = 'owner';",
+                )
+                .bind(self.discord_id)
+                .fetch_all(self.database)
                 .await
-                .unwrap();
-
-            let mut form_submit =
-                modal_interaction_collector::ModalInteractionCollectorBuilder::new(&*ctx).build();
-
-            while let Some(submission) = form_submit.next().await {
-                if let InputText(genshin_uid) = submission
-                    .data
-                    .components
-                    .get(0)
-                    .unwrap()
-                    .components
-                    .get(0)
-                    .unwrap()
-                {
-                    if let InputText(hoyo_cookie) = submission
-                        .data
-                        .components
-                        .get(1)
-                        .unwrap()
-                        .components
-                        .get(0)
-                        .unwrap()
-                    {
-                        let hoyo_cookie = &hoyo_cookie.value;
-                        let genshin_uid = &genshin_uid.value;
-
-                        let destructured_cookie = Client::destructure_cookie(hoyo_cookie);
-
-                        if let Err(error) = &destructured_cookie {
-                            interaction
-                                .create_interaction_response(&ctx, |res| {
-                                    res.kind(InteractionResponseType::ChannelMessageWithSource)
-                                        .interaction_response_data(|msg| {
-                                            msg.content(format!(
-                                                "Could not link account:\n{}",
-                                                error
-                                            ))
-                                        })
-                                })
-                                .await
-                                .unwrap();
-
-                            return;
-                        }
-
-                        let (cookie, ltuid, ltoken, cookie_token, account_id, lang) =
-                            destructured_cookie.unwrap();
-
-                        submission.create_interaction_response(&ctx, |res| {
-                            res.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|msg| {
-                                    msg.embed(|e| {
-                                        e.description(format!("The following account will be linked:\nGenshin UID:```rust\n{}```HoYoLab cookie:```properties\n{}```", genshin_uid, cookie.split(" ").map(|cookie| cookie.replace("=", " = ")).collect::<Vec<String>>().join("\n")))
-                                            .colour((122, 71, 72))
-                                    })
-                                })
-                        }).await.unwrap();
-
-                        let msg =
-                            submission
-                                .user
-                                .direct_message(&ctx.http, |msg| {
-                                    msg.components(|comp| {
-                                        comp.create_action_row(|row| {
-                                            row.create_button(|btn| {
-                                                btn.custom_id("cancel")
-                                                    .label("Cancel")
-                                                    .style(ButtonStyle::Secondary)
-                                            })
-                                            .create_button(|btn| {
-                                                btn.custom_id("proceed")
-                                                    .label("Link!")
-                                                    .style(ButtonStyle::Success)
-                                            })
-                                        })
-                                    })
-                                })
-                                .await
-                                .unwrap();
-
-                        let mut interaction_stream = msg
-                            .await_component_interactions(&*ctx)
-                            .timeout(Duration::from_secs(120))
-                            .build();
-
-                        while let Some(interaction) = interaction_stream.next().await {
-                            let action = &interaction.data.custom_id;
-
-                            msg.delete(&ctx).await.unwrap();
-
-                            if action == "proceed" {
-                                let count_config = sqlx::query(format!("SELECT COUNT(*) FROM users INNER JOIN config on users.genshin_uid = config.genshin_uid WHERE users.genshin_uid = \"{}\";", genshin_uid).as_str())
-                                    .fetch_one(database).await.unwrap().get::<u32, _>(0);
-
-                                if count_config == 0 {
-                                    sqlx::query(
-                                        format!(
-                                            "INSERT INTO config (genshin_uid) VALUES (\"{}\");",
-                                            genshin_uid
-                                        )
-                                        .as_str(),
-                                    )
-                                    .execute(database)
-                                    .await
-                                    .unwrap();
-                                }
-
-                                let count_hoyo_cookie = sqlx::query(format!("SELECT COUNT(*) FROM users INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id WHERE genshin_uid = \"{}\";", genshin_uid).as_str())
-                                    .fetch_one(database).await.unwrap().get::<u32, _>(0);
-
-                                if count_hoyo_cookie == 0 {
-                                    sqlx::query(format!("INSERT INTO hoyo_cookie (ltuid, ltoken, cookie_token, account_id, lang) VALUES (\"{}\", \"{}\", \"{}\", \"{}\", \"{}\");", ltuid, ltoken, cookie_token, account_id, lang).as_str())
-                                        .execute(database).await.unwrap();
-                                }
-
-                                let cookie_id = sqlx::query(
-                                    format!(
-                                        "SELECT cookie_id FROM hoyo_cookie WHERE ltuid = \"{}\";",
-                                        ltuid
-                                    )
-                                    .as_str(),
-                                )
-                                .fetch_one(database)
-                                .await
-                                .unwrap()
-                                .get::<u32, _>(0);
-
-                                let query = sqlx::query(format!("INSERT INTO users (discord_id, hoyo_cookie_id, genshin_uid) VALUES (\"{}\", {}, \"{}\");", discord_id, cookie_id, genshin_uid).as_str())
-                                    .execute(database).await;
-
-                                if let Err(e) = query {
-                                    interaction
-                                        .create_interaction_response(&ctx, |res| {
-                                            res.kind(
-                                                InteractionResponseType::ChannelMessageWithSource,
-                                            )
-                                            .interaction_response_data(|msg| {
-                                                msg.content(format!(
-                                                    "Could not link account:\n{}",
-                                                    e
-                                                ))
-                                            })
-                                        })
-                                        .await
-                                        .unwrap();
-
-                                    return;
-                                }
-
-                                interaction
-                                    .create_interaction_response(&ctx, |res| {
-                                        res.kind(InteractionResponseType::ChannelMessageWithSource)
-                                            .interaction_response_data(|msg| {
-                                                msg.content("Successfully linked account!")
-                                            })
-                                    })
-                                    .await
-                                    .unwrap();
-                            }
-                        }
+                .unwrap()
+                .into_iter()
+                .map::<String, _>(|user| user.get(0))
+                .collect::<Vec<String>>();
+
+                let (mut roles, invalid): (Vec<GameRole>, Vec<GameRole>) = roles
+                    .into_iter()
+                    .filter(|role| !linked.contains(&role.game_uid))
+                    .partition(|role| uid::parse(&role.game_uid).is_ok());
+
+                // A select menu holds at most 25 options
+                roles.truncate(25);
+
+                let skipped = invalid
+                    .iter()
+                    .map(|role| {
+                        format!(
+                            "{} - {}",
+                            role.game_uid,
+                            uid::parse(&role.game_uid).unwrap_err()
+                        )
+                    })
+                    .collect::<Vec<String>>();
+
+                if roles.is_empty() {
+                    if skipped.is_empty() {
+                        wizard
+                            .say("There are no unlinked Genshin accounts on this HoYoLab account.")
+                            .await;
+                    } else {
+                        wizard
+                            .say(format!(
+                                "None of the unlinked Genshin accounts on this HoYoLab account can be linked:```\n{}```",
+                                skipped.join("\n")
+                            ))
+                            .await;
                     }
+
+                    return Ok(Transition::Done);
                 }
+
+                Ok(Transition::Next(LinkStep::Select(
+                    credentials,
+                    roles,
+                    skipped,
+                )))
+            }
+            LinkStep::Select(credentials, roles, skipped) => {
+                let mut options = Vec::new();
+
+                for role in &roles {
+                    let mut option = CreateSelectMenuOption::new(&role.game_uid, &role.game_uid);
+                    option.description(format!("{} (AR {})", role.nickname, role.level));
+
+                    options.push(option);
+                }
+
+                let mut prompt = format!(
+                    "Found {} Genshin account(s) on this HoYoLab account. Please select the accounts you want to link",
+                    roles.len()
+                );
+
+                if !skipped.is_empty() {
+                    prompt.push_str(&format!(
+                        "\nThe following accounts cannot be linked:```\n{}```",
+                        skipped.join("\n")
+                    ));
+                }
+
+                let input = wizard
+                    .ask(Page::new(prompt).select("Select UIDs", options, roles.len() as u64))
+                    .await?;
+
+                let roles = roles
+                    .into_iter()
+                    .filter(|role| input.values.contains(&role.game_uid))
+                    .collect::<Vec<GameRole>>();
+
+                Ok(Transition::Next(LinkStep::Confirm(credentials, roles)))
+            }
+            LinkStep::Confirm(credentials, roles) => {
+                let accounts = roles
+                    .iter()
+                    .map(|role| {
+                        format!(
+                            "{} - {} (AR {}, {})",
+                            role.game_uid,
+                            role.nickname,
+                            role.level,
+                            uid::parse(&role.game_uid).unwrap()
+                        )
+                    })
+                    .collect::<Vec<String>>();
+
+                wizard
+                    .ask(
+                        Page::new(format!("The following accounts will be linked:```\n{}```HoYoLab cookie ({}):```properties\n{}```", accounts.join("\n"), credentials.version, credentials.header().split("; ").map(|cookie| cookie.replace("=", " = ")).collect::<Vec<String>>().join("\n")))
+                            .button("proceed", "Link!", ButtonStyle::Success),
+                    )
+                    .await?;
+
+                match store(self.database, self.discord_id, &credentials, &roles).await {
+                    Ok(()) => {
+                        wizard
+                            .say(format!("Successfully linked {} account(s)!", roles.len()))
+                            .await
+                    }
+                    Err(e) => {
+                        wizard
+                            .say(format!("Could not link account(s):\n{}", e))
+                            .await
+                    }
+                }
+
+                Ok(Transition::Done)
             }
         }
     }
 }
+
+/// Asks the user for their HoYoLab cookie, which they can paste into a form or upload as a file.
+/// Resolves to `None` if an uploaded file is too large or could not be read.
+pub async fn ask_cookie(wizard: &mut Wizard, instructions: String) -> Result<Option<String>, Stop> {
+    let page = Page::new(instructions).button("proceed", "Continue", ButtonStyle::Success);
+
+    match wizard.ask_with_upload(page).await? {
+        Answer::Input => Ok(Some(
+            wizard
+                .form(
+                    "Link form",
+                    &[("HoYoLab Token:", InputTextStyle::Paragraph)],
+                )
+                .await?
+                .remove(0),
+        )),
+        Answer::Upload(upload) => {
+            let attachment = &upload.attachments[0];
+
+            if attachment.size > MAX_COOKIE_FILE_SIZE {
+                wizard
+                    .say(format!(
+                        "Your cookie file is too large, it may be at most {} KB.",
+                        MAX_COOKIE_FILE_SIZE / 1024
+                    ))
+                    .await;
+
+                return Ok(None);
+            }
+
+            match attachment.download().await {
+                Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).to_string())),
+                Err(error) => {
+                    wizard
+                        .say(format!("Could not download your cookie file:\n{}", error))
+                        .await;
+
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Reads a cookie and fetches the game roles it has access to, proving it works.
+pub async fn verify(hoyo_cookie: &str) -> Result<(Credentials, Vec<GameRole>), String> {
+    let credentials = Credentials::parse(hoyo_cookie)
+        .map_err(|error| format!("Could not read your HoYoLab cookie: {}", error))?;
+
+    let roles = hoyolab::Client::new(credentials.clone())
+        .game_roles()
+        .await
+        .map_err(|error| format!("Could not verify your HoYoLab account: {}", error))?;
+
+    Ok((credentials, roles))
+}
+
+/// Stores the cookie and accounts in one transaction, so a failure leaves nothing behind.
+async fn store(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+    credentials: &Credentials,
+    roles: &[GameRole],
+) -> Result<(), sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    // All accounts share the cookie of the HoYoLab account they belong to, which is refreshed
+    // with the one just verified
+    let cookie_id = sqlx::query("INSERT INTO hoyo_cookie (ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, status, last_verified_at) VALUES (?, ?, ?, ?, ?, ?, ?, 'healthy', CURRENT_TIMESTAMP) \
+                                 ON CONFLICT (ltuid) DO UPDATE SET ltoken = excluded.ltoken, cookie_token = excluded.cookie_token, account_id = excluded.account_id, lang = excluded.lang, version = excluded.version, account_mid = excluded.account_mid, status = excluded.status, last_verified_at = excluded.last_verified_at \
+                                 RETURNING cookie_id;")
+        .bind(&credentials.ltuid)
+        .bind(&credentials.ltoken)
+        .bind(&credentials.cookie_token)
+        .bind(&credentials.account_id)
+        .bind(&credentials.lang)
+        .bind(credentials.version as u32)
+        .bind(&credentials.account_mid)
+        .fetch_one(&mut tx)
+        .await?
+        .get::<u32, _>(0);
+
+    for role in roles {
+        let region = uid::parse(&role.game_uid).unwrap();
+
+        sqlx::query("INSERT OR IGNORE INTO config (genshin_uid, region) VALUES (?, ?);")
+            .bind(&role.game_uid)
+            .bind(region.id())
+            .execute(&mut tx)
+            .await?;
+
+        sqlx::query("INSERT INTO users (discord_id, hoyo_cookie_id, genshin_uid, role) VALUES (?, ?, ?, 'owner') \
+                     ON CONFLICT (discord_id, genshin_uid) DO UPDATE SET hoyo_cookie_id = excluded.hoyo_cookie_id, role = 'owner';")
+        .bind(discord_id)
+        .bind(cookie_id)
+        .bind(&role.game_uid)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
@@ -1,6 +1,13 @@
 pub mod accounts;
+pub mod alias;
 pub mod claim_code;
 pub mod claim_daily;
+pub mod forgetme;
+pub mod integrity;
 pub mod link;
+pub mod mydata;
+pub mod relink;
+pub mod share;
 pub mod submitcode;
+pub mod transfer;
 pub mod unlink;
@@ -0,0 +1,213 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::channel::AttachmentType;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::prelude::Context;
+use sqlx::Row;
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    let discord_id = command.user.id.0 as i64;
+    let data = collect(database, discord_id).await;
+
+    let success = command
+        .user
+        .direct_message(&ctx.http, |msg| {
+            msg.content("This is all data the bot stores about you. Tokens are masked.")
+                .add_file(AttachmentType::Bytes {
+                    data: Cow::from(serde_json::to_vec_pretty(&data).unwrap()),
+                    filename: "mydata.json".to_string(),
+                })
+        })
+        .await;
+
+    let content = match success {
+        Ok(_) => "Your data has been sent to your DMs.".to_string(),
+        Err(error) => format!("Could not send your data:\n{}", error),
+    };
+
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content(content))
+        })
+        .await
+        .unwrap();
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("mydata")
+        .description("Get a copy of the data the bot stores about you")
+}
+
+async fn collect(database: &sqlx::SqlitePool, discord_id: i64) -> Value {
+    let users = sqlx::query("SELECT genshin_uid, hoyo_cookie_id, role, alias, created_at, updated_at FROM users WHERE discord_id = ?;")
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "genshin_uid": row.get::<String, _>("genshin_uid"),
+                "hoyo_cookie_id": row.get::<u32, _>("hoyo_cookie_id"),
+                "role": row.get::<String, _>("role"),
+                "alias": row.get::<Option<String>, _>("alias"),
+                "created_at": row.get::<String, _>("created_at"),
+                "updated_at": row.get::<String, _>("updated_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let unlinked_users = sqlx::query("SELECT genshin_uid, hoyo_cookie_id, linked_at, unlinked_at FROM unlinked_users WHERE discord_id = ?;")
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "genshin_uid": row.get::<String, _>("genshin_uid"),
+                "hoyo_cookie_id": row.get::<u32, _>("hoyo_cookie_id"),
+                "linked_at": row.get::<String, _>("linked_at"),
+                "unlinked_at": row.get::<String, _>("unlinked_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let config = sqlx::query("SELECT DISTINCT config.* FROM config \
+                              WHERE genshin_uid IN (SELECT genshin_uid FROM users WHERE discord_id = ? UNION SELECT genshin_uid FROM unlinked_users WHERE discord_id = ?);")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "genshin_uid": row.get::<String, _>("genshin_uid"),
+                "auto_claim_codes": row.get::<bool, _>("auto_claim_codes"),
+                "region": row.get::<Option<String>, _>("region"),
+                "created_at": row.get::<String, _>("created_at"),
+                "updated_at": row.get::<String, _>("updated_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    // The cookies of the accounts shared with a viewer belong to their owners
+    let hoyo_cookies = sqlx::query("SELECT DISTINCT hoyo_cookie.* FROM hoyo_cookie \
+                                    WHERE cookie_id IN (SELECT hoyo_cookie_id FROM users WHERE discord_id = ? AND role = 'owner' \
+                                    UNION SELECT hoyo_cookie_id FROM unlinked_users WHERE discord_id = ? AND role = 'owner');")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "cookie_id": row.get::<u32, _>("cookie_id"),
+                "version": row.get::<u32, _>("version"),
+                "ltuid": row.get::<String, _>("ltuid"),
+                "ltoken": mask(&row.get::<String, _>("ltoken")),
+                "cookie_token": mask(&row.get::<String, _>("cookie_token")),
+                "account_id": row.get::<String, _>("account_id"),
+                "account_mid": row.get::<Option<String>, _>("account_mid"),
+                "lang": row.get::<String, _>("lang"),
+                "status": row.get::<String, _>("status"),
+                "last_verified_at": row.get::<Option<String>, _>("last_verified_at"),
+                "created_at": row.get::<String, _>("created_at"),
+                "updated_at": row.get::<String, _>("updated_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let transfers = sqlx::query("SELECT transfers.transfer_id, from_discord_id, to_discord_id, group_concat(genshin_uid) AS genshin_uids, transfers.created_at FROM transfers \
+                                 INNER JOIN transfer_accounts ON transfers.transfer_id = transfer_accounts.transfer_id \
+                                 WHERE from_discord_id = ? OR to_discord_id = ? GROUP BY transfers.transfer_id;")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "transfer_id": row.get::<i64, _>("transfer_id"),
+                "from_discord_id": row.get::<i64, _>("from_discord_id").to_string(),
+                "to_discord_id": row.get::<i64, _>("to_discord_id").to_string(),
+                "genshin_uids": row.get::<String, _>("genshin_uids").split(',').collect::<Vec<&str>>(),
+                "created_at": row.get::<String, _>("created_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let claim_history = sqlx::query("SELECT genshin_uid, kind, code, outcome, error, attempts, created_at FROM claim_history \
+                                     WHERE genshin_uid IN (SELECT genshin_uid FROM users WHERE discord_id = ? UNION SELECT genshin_uid FROM unlinked_users WHERE discord_id = ?) \
+                                     ORDER BY claim_id;")
+        .bind(discord_id)
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "genshin_uid": row.get::<String, _>("genshin_uid"),
+                "kind": row.get::<String, _>("kind"),
+                "code": row.get::<Option<String>, _>("code"),
+                "outcome": row.get::<String, _>("outcome"),
+                "error": row.get::<Option<String>, _>("error"),
+                "attempts": row.get::<u32, _>("attempts"),
+                "created_at": row.get::<String, _>("created_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let pending_claims = sqlx::query("SELECT genshin_uid, code, created_at FROM pending_claims \
+                                      WHERE genshin_uid IN (SELECT genshin_uid FROM users WHERE discord_id = ?);")
+        .bind(discord_id)
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            json!({
+                "genshin_uid": row.get::<String, _>("genshin_uid"),
+                "code": row.get::<String, _>("code"),
+                "created_at": row.get::<String, _>("created_at"),
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    json!({
+        "discord_id": discord_id.to_string(),
+        "users": users,
+        "unlinked_users": unlinked_users,
+        "config": config,
+        "hoyo_cookies": hoyo_cookies,
+        "transfers": transfers,
+        "claim_history": claim_history,
+        "pending_claims": pending_claims,
+    })
+}
+
+/// Keeps only the last few characters of a token, enough to tell tokens apart.
+fn mask(token: &str) -> String {
+    let visible = token.chars().count().saturating_sub(4);
+
+    token
+        .chars()
+        .enumerate()
+        .map(|(index, char)| if index < visible { '*' } else { char })
+        .collect()
+}
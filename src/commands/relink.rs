@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommand, CreateSelectMenuOption};
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::Timestamp;
+use serenity::prelude::Context;
+use sqlx::Row;
+
+use crate::alias;
+use crate::commands::link;
+use crate::hoyolab::Credentials;
+use crate::wizard::{Flow, Page, Stop, Transition, Wizard};
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    let discord_id = command.user.id.0 as i64;
+
+    let usercount =
+        sqlx::query("SELECT COUNT(*) FROM users WHERE discord_id = ? AND role = 'owner';")
+            .bind(discord_id)
+            .fetch_one(database)
+            .await
+            .unwrap()
+            .get::<u32, _>(0);
+
+    if usercount == 0 {
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        msg.content("You do not own any linked accounts")
+                    })
+            })
+            .await
+            .unwrap();
+
+        return;
+    }
+
+    command
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.embed(|e| {
+                        e.title("Update token")
+                            .description(
+                                "Do you want to update the HoYoLab token of a linked account?",
+                            )
+                            .colour((122, 71, 72))
+                            .footer(|f| {
+                                f.icon_url(command.user.face()).text(format!(
+                                    "Requested by {}#{}",
+                                    command.user.name, command.user.discriminator
+                                ))
+                            })
+                            .timestamp(Timestamp::now())
+                    })
+                })
+        })
+        .await
+        .unwrap();
+
+    Wizard::start(ctx, "relink", &command.user, None)
+        .await
+        .run(
+            &mut RelinkFlow::new(database, discord_id),
+            RelinkStep::Start,
+        )
+        .await;
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("relink")
+        .description("Update the HoYoLab token of a linked account")
+}
+
+/// Starts the `/relink` flow from the account selection, for buttons that skip its confirmation.
+pub async fn relink(
+    database: &sqlx::SqlitePool,
+    interaction: Arc<MessageComponentInteraction>,
+    ctx: Arc<Context>,
+) {
+    let discord_id = interaction.user.id.0 as i64;
+    let user = interaction.user.clone();
+
+    Wizard::start(ctx, "relink", &user, Some(interaction))
+        .await
+        .run(
+            &mut RelinkFlow::new(database, discord_id),
+            RelinkStep::Select,
+        )
+        .await;
+}
+
+#[derive(Clone)]
+enum RelinkStep {
+    Start,
+    Select,
+    Cookie(String),
+}
+
+struct RelinkFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    discord_id: i64,
+}
+
+impl<'a> RelinkFlow<'a> {
+    fn new(database: &'a sqlx::SqlitePool, discord_id: i64) -> RelinkFlow<'a> {
+        RelinkFlow {
+            database,
+            discord_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Flow for RelinkFlow<'_> {
+    type Step = RelinkStep;
+
+    async fn step(
+        &mut self,
+        wizard: &mut Wizard,
+        step: RelinkStep,
+    ) -> Result<Transition<RelinkStep>, Stop> {
+        match step {
+            RelinkStep::Start => {
+                wizard
+                    .ask(Page::default().button("proceed", "Update token", ButtonStyle::Success))
+                    .await?;
+
+                Ok(Transition::Next(RelinkStep::Select))
+            }
+            RelinkStep::Select => {
+                // Viewers cannot change the token of the accounts shared with them
+                let options = sqlx::query(
+                    "SELECT genshin_uid, alias FROM users WHERE discord_id = ? AND role = 'owner';",
+                )
+                .bind(self.discord_id)
+                .fetch_all(self.database)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|user| alias::option(&user))
+                .collect::<Vec<CreateSelectMenuOption>>();
+
+                // The buttons of an old `/accounts` message can outlive the accounts
+                if options.is_empty() {
+                    wizard.say("You do not own any linked accounts").await;
+
+                    return Ok(Transition::Done);
+                }
+
+                let input = wizard
+                    .ask(
+                        Page::new(
+                            "Please select the account you want to update the HoYoLab token of",
+                        )
+                        .select("Select UID", options, 1),
+                    )
+                    .await?;
+
+                Ok(Transition::Next(RelinkStep::Cookie(
+                    input.values[0].clone(),
+                )))
+            }
+            RelinkStep::Cookie(genshin_uid) => {
+                let hoyo_cookie =
+                    match link::ask_cookie(wizard, link::COOKIE_INSTRUCTIONS.to_string()).await? {
+                        Some(hoyo_cookie) => hoyo_cookie,
+                        None => return Ok(Transition::Stay(RelinkStep::Cookie(genshin_uid))),
+                    };
+
+                let error = match link::verify(&hoyo_cookie).await {
+                    Ok((credentials, roles))
+                        if roles.iter().any(|role| role.game_uid == genshin_uid) =>
+                    {
+                        match self.update(&genshin_uid, &credentials).await {
+                            Ok(()) => {
+                                let names = alias::names(
+                                    self.database,
+                                    self.discord_id,
+                                    std::slice::from_ref(&genshin_uid),
+                                )
+                                .await;
+
+                                wizard
+                                    .say(format!(
+                                        "Successfully updated the HoYoLab token of {}!",
+                                        names[0]
+                                    ))
+                                    .await
+                            }
+                            Err(e) => wizard.say(format!("Could not update token:\n{}", e)).await,
+                        }
+
+                        return Ok(Transition::Done);
+                    }
+                    Ok(_) => format!(
+                        "Genshin UID `{}` does not belong to this HoYoLab account",
+                        genshin_uid
+                    ),
+                    Err(error) => error,
+                };
+
+                wizard.say(format!("{}.\nPlease try again.", error)).await;
+
+                Ok(Transition::Stay(RelinkStep::Cookie(genshin_uid)))
+            }
+        }
+    }
+}
+
+impl RelinkFlow<'_> {
+    /// Replaces the HoYoLab cookie of a linked account in place, so its settings are kept.
+    async fn update(
+        &self,
+        genshin_uid: &str,
+        credentials: &Credentials,
+    ) -> Result<(), sqlx::Error> {
+        let cookie_id = sqlx::query(
+            "SELECT hoyo_cookie_id FROM users WHERE (discord_id, genshin_uid) = (?, ?);",
+        )
+        .bind(self.discord_id)
+        .bind(genshin_uid)
+        .fetch_one(self.database)
+        .await?
+        .get::<u32, _>(0);
+
+        sqlx::query("UPDATE hoyo_cookie SET ltuid = ?, ltoken = ?, cookie_token = ?, account_id = ?, lang = ?, version = ?, account_mid = ?, status = 'healthy', last_verified_at = CURRENT_TIMESTAMP WHERE cookie_id = ?;")
+            .bind(&credentials.ltuid)
+            .bind(&credentials.ltoken)
+            .bind(&credentials.cookie_token)
+            .bind(&credentials.account_id)
+            .bind(&credentials.lang)
+            .bind(credentials.version as u32)
+            .bind(&credentials.account_mid)
+            .bind(cookie_id)
+            .execute(self.database)
+            .await?;
+
+        Ok(())
+    }
+}
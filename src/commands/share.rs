@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommand, CreateSelectMenuOption};
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::prelude::User;
+use serenity::model::Timestamp;
+use serenity::prelude::Context;
+use sqlx::{Sqlite, Transaction};
+
+use crate::alias;
+use crate::wizard::{Flow, Page, Stop, Transition, Wizard};
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    let viewer = match command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.resolved.as_ref())
+    {
+        Some(CommandDataOptionValue::User(user, _)) => user.clone(),
+        _ => return,
+    };
+
+    let error = if viewer.bot {
+        Some("You cannot share accounts with a bot.")
+    } else if viewer.id == command.user.id {
+        Some("You cannot share accounts with yourself.")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|msg| msg.content(error))
+            })
+            .await
+            .unwrap();
+
+        return;
+    }
+
+    command
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.embed(|e| {
+                        e.title("Share accounts")
+                            .description(format!(
+                                "Which accounts do you want to share with {}#{}? They will receive the account's notifications, but cannot claim rewards, update its token or change its settings.",
+                                viewer.name, viewer.discriminator
+                            ))
+                            .colour((122, 71, 72))
+                            .footer(|f| {
+                                f.icon_url(command.user.face()).text(format!(
+                                    "Requested by {}#{}",
+                                    command.user.name, command.user.discriminator
+                                ))
+                            })
+                            .timestamp(Timestamp::now())
+                    })
+                })
+        })
+        .await
+        .unwrap();
+
+    Wizard::start(ctx.clone(), "share", &command.user, None)
+        .await
+        .run(
+            &mut ShareFlow {
+                database,
+                ctx,
+                owner: command.user.clone(),
+                viewer,
+            },
+            (),
+        )
+        .await;
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("share")
+        .description("Share linked accounts with another Discord user as a viewer")
+        .create_option(|option| {
+            option
+                .name("user")
+                .description("The Discord user to share the accounts with")
+                .kind(CommandOptionType::User)
+                .required(true)
+        })
+}
+
+struct ShareFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    ctx: Arc<Context>,
+    owner: User,
+    viewer: User,
+}
+
+#[async_trait]
+impl Flow for ShareFlow<'_> {
+    type Step = ();
+
+    async fn step(&mut self, wizard: &mut Wizard, _: ()) -> Result<Transition<()>, Stop> {
+        let options = sqlx::query(
+            "SELECT genshin_uid, alias FROM users WHERE discord_id = ? AND role = 'owner';",
+        )
+        .bind(self.owner.id.0 as i64)
+        .fetch_all(self.database)
+        .await
+        .unwrap()
+        .into_iter()
+        .take(25)
+        .map(|user| alias::option(&user))
+        .collect::<Vec<CreateSelectMenuOption>>();
+
+        if options.is_empty() {
+            wizard.say("You do not own any linked accounts").await;
+
+            return Ok(Transition::Done);
+        }
+
+        let max_values = options.len() as u64;
+
+        let input = wizard
+            .ask(Page::new("Please select the accounts to share").select(
+                "Select UIDs",
+                options,
+                max_values,
+            ))
+            .await?;
+
+        match share(
+            self.database,
+            self.owner.id.0 as i64,
+            self.viewer.id.0 as i64,
+            &input.values,
+        )
+        .await
+        {
+            Ok(()) => {
+                let names =
+                    alias::names(self.database, self.owner.id.0 as i64, &input.values).await;
+
+                let success = self
+                    .viewer
+                    .direct_message(&self.ctx.http, |msg| {
+                        msg.content(format!(
+                            "{}#{} shared the accounts `{}` with you. You will receive their notifications, and can stop doing so with `/unlink`.",
+                            self.owner.name,
+                            self.owner.discriminator,
+                            names.join("`, `")
+                        ))
+                    })
+                    .await;
+
+                if let Err(error) = success {
+                    println!(
+                        "Error sending share notice to `{}`:\n {}",
+                        self.viewer.id, error
+                    );
+                }
+
+                wizard
+                    .say(format!(
+                        "Successfully shared {} account(s) with {}#{}!",
+                        input.values.len(),
+                        self.viewer.name,
+                        self.viewer.discriminator
+                    ))
+                    .await
+            }
+            Err(e) => {
+                wizard
+                    .say(format!("Could not share account(s):\n{}", e))
+                    .await
+            }
+        }
+
+        Ok(Transition::Done)
+    }
+}
+
+/// Links the accounts to the viewer in one transaction. Accounts they already have linked are left
+/// as they are.
+async fn share(
+    database: &sqlx::SqlitePool,
+    owner_discord_id: i64,
+    viewer_discord_id: i64,
+    genshin_uids: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    for genshin_uid in genshin_uids {
+        sqlx::query("INSERT OR IGNORE INTO users (discord_id, hoyo_cookie_id, genshin_uid, role) \
+                     SELECT ?, hoyo_cookie_id, genshin_uid, 'viewer' FROM users WHERE (discord_id, genshin_uid, role) = (?, ?, 'owner');")
+            .bind(viewer_discord_id)
+            .bind(owner_discord_id)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Drops the viewer links of an account once no owner is left, as nobody could keep its cookie up
+/// to date anymore. Returns how many links were dropped.
+pub async fn unshare_ownerless(
+    tx: &mut Transaction<'_, Sqlite>,
+    genshin_uid: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut count = 0;
+
+    for table in ["users", "unlinked_users"] {
+        count += sqlx::query(&format!(
+            "DELETE FROM {} WHERE genshin_uid = ? AND role = 'viewer' \
+             AND NOT EXISTS (SELECT 1 FROM users WHERE genshin_uid = ? AND role = 'owner');",
+            table
+        ))
+        .bind(genshin_uid)
+        .bind(genshin_uid)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(count)
+}
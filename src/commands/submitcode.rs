@@ -1,25 +1,29 @@
 use std::sync::Arc;
 
-use hoyo_api::prelude::*;
 use serenity::builder::CreateApplicationCommand;
+use serenity::http::Http;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::prelude::command::CommandOptionType;
 use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
 use serenity::model::prelude::interaction::InteractionResponseType;
 use serenity::model::prelude::UserId;
 use serenity::prelude::Context;
+use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
 
+use crate::hoyolab::{self, ClaimOutcome, Credentials};
+use crate::{alias, history};
+
 pub async fn run(
     database: &sqlx::SqlitePool,
     command: &ApplicationCommandInteraction,
     ctx: Arc<Context>,
 ) {
-    let discord_id = command.user.id.0.to_string();
+    let discord_id = command.user.id.0;
 
     let usercount = sqlx::query(
         format!(
-            "SELECT COUNT(*) FROM users WHERE discord_id = \"{}\";",
+            "SELECT COUNT(*) FROM users WHERE discord_id = {};",
             discord_id
         )
         .as_str(),
@@ -41,7 +45,7 @@ pub async fn run(
     if let CommandDataOptionValue::String(code) = command
         .data
         .options
-        .get(0)
+        .first()
         .unwrap()
         .resolved
         .as_ref()
@@ -88,94 +92,29 @@ pub async fn run(
             return;
         }
 
+        let content = if hoyolab::is_available() {
+            format!("Submitted code {}!", code)
+        } else {
+            format!("Submitted code {}! HoYoLab appears to be down, so it will be claimed once HoYoLab is back.", code)
+        };
+
         command
             .create_interaction_response(&ctx.http, |response| {
                 response
                     .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|msg| {
-                        msg.content(format!("Submitted code {}!", code))
-                    })
+                    .interaction_response_data(|msg| msg.content(content))
             })
             .await
             .unwrap();
 
         // Claim code on all linked accounts
-        let users = sqlx::query(format!("SELECT DISTINCT ltuid, ltoken, cookie_token, account_id, lang, users.genshin_uid FROM users \
-                                                              INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id \
-                                                              INNER JOIN config on users.genshin_uid = config.genshin_uid \
-                                                              WHERE config.auto_claim_codes = 1;").as_str())
-            .fetch_all(database).await.unwrap();
-
-        for user in users {
-            let hoyo_cookie = Cookie::CookieParsed(
-                user.get(0),
-                user.get(1),
-                user.get(2),
-                user.get(3),
-                user.get(4),
-            );
-            let genshin_uid: String = user.get(5);
-
-            let hoyo_client = Client::new(hoyo_cookie, genshin_uid.as_str());
-
-            if let Err(error) = hoyo_client {
-                command
-                    .user
-                    .direct_message(&ctx.http, |msg| {
-                        msg.content(format!("Could not connect hoyo client: {}", error))
-                    })
-                    .await
-                    .unwrap();
-
-                return;
-            }
-
-            let code = code.clone();
-            let uid = genshin_uid.clone();
-
-            let output = tokio::task::spawn_blocking(move || {
-                if let Err(error) = hoyo_client.unwrap().claim_code(&code) {
-                    format!(
-                        "Error auto-claiming code `{}` on {}: `{}`",
-                        code, uid, error
-                    )
-                } else {
-                    format!("Successfully auto-claimed code `{}` on {}", code, uid)
-                }
-            })
+        let users = sqlx::query(&format!("{};", AUTO_CLAIM_USERS))
+            .fetch_all(database)
             .await
             .unwrap();
 
-            let discord_ids = sqlx::query(
-                format!(
-                    "SELECT discord_id FROM users WHERE genshin_uid = {};",
-                    genshin_uid
-                )
-                .as_str(),
-            )
-            .fetch_all(database)
-            .await
-            .unwrap()
-            .into_iter()
-            .map::<String, _>(|id| id.get(0))
-            .collect::<Vec<String>>();
-
-            for discord_id in discord_ids {
-                let success = UserId(discord_id.trim().parse::<u64>().unwrap())
-                    .create_dm_channel(&ctx)
-                    .await
-                    .unwrap()
-                    .send_message(&ctx.http, |msg| msg.content(output.clone()))
-                    .await;
-
-                if let Err(error) = success {
-                    println!(
-                        "Error sending confirmation to `{}`:\n {}",
-                        discord_id.trim(),
-                        error
-                    );
-                }
-            }
+        for user in users {
+            auto_claim(database, &ctx.http, &user, code).await;
         }
     }
 }
@@ -192,3 +131,86 @@ pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicatio
                 .required(true)
         })
 }
+
+/// Accounts codes are claimed on automatically, with everything `auto_claim` needs.
+pub const AUTO_CLAIM_USERS: &str = "SELECT DISTINCT ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, users.genshin_uid, region FROM users \
+                                    INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id \
+                                    INNER JOIN config on users.genshin_uid = config.genshin_uid \
+                                    WHERE config.auto_claim_codes = 1 AND hoyo_cookie.status != 'dead'";
+
+/// Claims the code on the account and lets everyone who linked it know. While HoYoLab is down the
+/// claim is queued instead, and `false` is returned.
+pub async fn auto_claim(
+    database: &sqlx::SqlitePool,
+    http: &Http,
+    user: &SqliteRow,
+    code: &str,
+) -> bool {
+    let hoyo_client = hoyolab::Client::new(Credentials::from_row(user));
+    let genshin_uid: String = user.get("genshin_uid");
+
+    let (outcome, attempts) = match user.get::<Option<String>, _>("region") {
+        Some(region) => {
+            hoyolab::with_retry(|| hoyo_client.claim_code(&genshin_uid, &region, code)).await
+        }
+        None => (ClaimOutcome::Failed("unknown server region".to_string()), 0),
+    };
+
+    if outcome == ClaimOutcome::Unavailable {
+        sqlx::query("INSERT OR IGNORE INTO pending_claims (genshin_uid, code) VALUES (?, ?);")
+            .bind(&genshin_uid)
+            .bind(code)
+            .execute(database)
+            .await
+            .unwrap();
+
+        return false;
+    }
+
+    history::record(database, &genshin_uid, Some(code), &outcome, attempts).await;
+
+    let recipients = sqlx::query("SELECT discord_id, alias FROM users WHERE genshin_uid = ?;")
+        .bind(&genshin_uid)
+        .fetch_all(database)
+        .await
+        .unwrap();
+
+    for recipient in recipients {
+        let discord_id = recipient.get::<i64, _>("discord_id");
+
+        // Every recipient sees the account under their own alias
+        let account = alias::name(
+            &genshin_uid,
+            recipient.get::<Option<String>, _>("alias").as_deref(),
+        );
+
+        let output = match &outcome {
+            ClaimOutcome::Claimed => {
+                format!("Successfully auto-claimed code `{}` on {}", code, account)
+            }
+            // Nothing to report, the code was claimed by hand before
+            ClaimOutcome::RedeemedAlready => continue,
+            _ => format!(
+                "Error auto-claiming code `{}` on {}: `{}`",
+                code, account, outcome
+            ),
+        };
+
+        let success = match UserId(discord_id as u64).create_dm_channel(http).await {
+            Ok(channel) => channel
+                .send_message(http, |msg| msg.content(output))
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = success {
+            println!(
+                "Error sending confirmation to `{}`:\n {}",
+                discord_id, error
+            );
+        }
+    }
+
+    true
+}
@@ -0,0 +1,438 @@
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommand, CreateComponents, CreateSelectMenuOption};
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::prelude::{User, UserId};
+use serenity::model::Timestamp;
+use serenity::prelude::Context;
+use sqlx::Row;
+
+use crate::alias;
+use crate::wizard::{Flow, Page, Stop, Transition, Wizard};
+
+pub async fn run(
+    database: &sqlx::SqlitePool,
+    command: &ApplicationCommandInteraction,
+    ctx: Arc<Context>,
+) {
+    let recipient = match command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.resolved.as_ref())
+    {
+        Some(CommandDataOptionValue::User(user, _)) => user.clone(),
+        _ => return,
+    };
+
+    let error = if recipient.bot {
+        Some("You cannot transfer accounts to a bot.")
+    } else if recipient.id == command.user.id {
+        Some("You cannot transfer accounts to yourself.")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|msg| msg.content(error))
+            })
+            .await
+            .unwrap();
+
+        return;
+    }
+
+    command
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.embed(|e| {
+                        e.title("Transfer accounts")
+                            .description(format!(
+                                "Which accounts do you want to transfer to {}#{}?",
+                                recipient.name, recipient.discriminator
+                            ))
+                            .colour((122, 71, 72))
+                            .footer(|f| {
+                                f.icon_url(command.user.face()).text(format!(
+                                    "Requested by {}#{}",
+                                    command.user.name, command.user.discriminator
+                                ))
+                            })
+                            .timestamp(Timestamp::now())
+                    })
+                })
+        })
+        .await
+        .unwrap();
+
+    Wizard::start(ctx.clone(), "transfer", &command.user, None)
+        .await
+        .run(
+            &mut TransferFlow {
+                database,
+                ctx,
+                sender: command.user.clone(),
+                recipient,
+            },
+            TransferStep::Select,
+        )
+        .await;
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("transfer")
+        .description("Transfer linked accounts to another Discord user")
+        .create_option(|option| {
+            option
+                .name("user")
+                .description("The Discord user to transfer the accounts to")
+                .kind(CommandOptionType::User)
+                .required(true)
+        })
+}
+
+#[derive(Clone)]
+enum TransferStep {
+    Select,
+    Confirm(Vec<String>),
+}
+
+struct TransferFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    ctx: Arc<Context>,
+    sender: User,
+    recipient: User,
+}
+
+#[async_trait]
+impl Flow for TransferFlow<'_> {
+    type Step = TransferStep;
+
+    async fn step(
+        &mut self,
+        wizard: &mut Wizard,
+        step: TransferStep,
+    ) -> Result<Transition<TransferStep>, Stop> {
+        match step {
+            TransferStep::Select => {
+                let options = sqlx::query(
+                    "SELECT genshin_uid, alias FROM users WHERE discord_id = ? AND role = 'owner';",
+                )
+                .bind(self.sender.id.0 as i64)
+                .fetch_all(self.database)
+                .await
+                .unwrap()
+                .into_iter()
+                .take(25)
+                .map(|user| alias::option(&user))
+                .collect::<Vec<CreateSelectMenuOption>>();
+
+                if options.is_empty() {
+                    wizard.say("You do not own any linked accounts").await;
+
+                    return Ok(Transition::Done);
+                }
+
+                let max_values = options.len() as u64;
+
+                let input = wizard
+                    .ask(Page::new("Please select the accounts to transfer").select(
+                        "Select UIDs",
+                        options,
+                        max_values,
+                    ))
+                    .await?;
+
+                Ok(Transition::Next(TransferStep::Confirm(input.values)))
+            }
+            TransferStep::Confirm(genshin_uids) => {
+                let names =
+                    alias::names(self.database, self.sender.id.0 as i64, &genshin_uids).await;
+
+                wizard
+                    .ask(
+                        Page::new(format!(
+                            "The following accounts will be offered to {}#{}:```\n{}```They stay linked to you until they accept. Proceed?",
+                            self.recipient.name,
+                            self.recipient.discriminator,
+                            names.join("\n")
+                        ))
+                        .button("proceed", "Transfer", ButtonStyle::Success),
+                    )
+                    .await?;
+
+                let transfer_id = match offer(
+                    self.database,
+                    self.sender.id.0 as i64,
+                    self.recipient.id.0 as i64,
+                    &genshin_uids,
+                )
+                .await
+                {
+                    Ok(transfer_id) => transfer_id,
+                    Err(e) => {
+                        wizard
+                            .say(format!("Could not transfer account(s):\n{}", e))
+                            .await;
+
+                        return Ok(Transition::Done);
+                    }
+                };
+
+                let success = self
+                    .recipient
+                    .direct_message(&self.ctx.http, |msg| {
+                        msg.embed(|e| {
+                            e.title("Account transfer")
+                                .description(format!(
+                                    "{}#{} wants to transfer the following accounts to you:```\n{}```",
+                                    self.sender.name,
+                                    self.sender.discriminator,
+                                    names.join("\n")
+                                ))
+                                .colour((122, 71, 72))
+                        })
+                        .components(|comp| {
+                            comp.create_action_row(|row| {
+                                row.create_button(|btn| {
+                                    btn.custom_id(format!("transfer_decline:{}", transfer_id))
+                                        .label("Decline")
+                                        .style(ButtonStyle::Secondary)
+                                })
+                                .create_button(|btn| {
+                                    btn.custom_id(format!("transfer_accept:{}", transfer_id))
+                                        .label("Accept")
+                                        .style(ButtonStyle::Success)
+                                })
+                            })
+                        })
+                    })
+                    .await;
+
+                match success {
+                    Ok(_) => {
+                        wizard
+                            .say(format!(
+                                "The transfer has been sent to {}#{}.",
+                                self.recipient.name, self.recipient.discriminator
+                            ))
+                            .await
+                    }
+                    Err(error) => {
+                        sqlx::query("DELETE FROM transfers WHERE transfer_id = ?;")
+                            .bind(transfer_id)
+                            .execute(self.database)
+                            .await
+                            .unwrap();
+
+                        wizard
+                            .say(format!(
+                                "Could not send the transfer to {}#{}:\n{}",
+                                self.recipient.name, self.recipient.discriminator, error
+                            ))
+                            .await
+                    }
+                }
+
+                Ok(Transition::Done)
+            }
+        }
+    }
+}
+
+async fn offer(
+    database: &sqlx::SqlitePool,
+    from_discord_id: i64,
+    to_discord_id: i64,
+    genshin_uids: &[String],
+) -> Result<i64, sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    let transfer_id = sqlx::query(
+        "INSERT INTO transfers (from_discord_id, to_discord_id) VALUES (?, ?) RETURNING transfer_id;",
+    )
+    .bind(from_discord_id)
+    .bind(to_discord_id)
+    .fetch_one(&mut tx)
+    .await?
+    .get::<i64, _>(0);
+
+    for genshin_uid in genshin_uids {
+        sqlx::query("INSERT INTO transfer_accounts (transfer_id, genshin_uid) VALUES (?, ?);")
+            .bind(transfer_id)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(transfer_id)
+}
+
+/// Handles the recipient accepting or declining a transfer.
+pub async fn answer(
+    database: &sqlx::SqlitePool,
+    interaction: Arc<MessageComponentInteraction>,
+    ctx: Arc<Context>,
+    transfer_id: i64,
+    accept: bool,
+) {
+    let transfer = sqlx::query(
+        "SELECT from_discord_id FROM transfers WHERE (transfer_id, to_discord_id) = (?, ?);",
+    )
+    .bind(transfer_id)
+    .bind(interaction.user.id.0 as i64)
+    .fetch_optional(database)
+    .await
+    .unwrap();
+
+    let (content, notice) = match transfer {
+        None => ("This transfer is no longer available.".to_string(), None),
+        Some(transfer) => {
+            let from_discord_id = transfer.get::<i64, _>(0);
+
+            let result = if accept {
+                complete(
+                    database,
+                    transfer_id,
+                    from_discord_id,
+                    interaction.user.id.0 as i64,
+                )
+                .await
+            } else {
+                sqlx::query("DELETE FROM transfers WHERE transfer_id = ?;")
+                    .bind(transfer_id)
+                    .execute(database)
+                    .await
+                    .map(|_| Vec::new())
+            };
+
+            match result {
+                Ok(names) if accept => (
+                    format!(
+                        "Successfully transferred {} account(s) to you!",
+                        names.len()
+                    ),
+                    Some((
+                        from_discord_id,
+                        format!(
+                            "{}#{} accepted the transfer of `{}`.",
+                            interaction.user.name,
+                            interaction.user.discriminator,
+                            names.join("`, `")
+                        ),
+                    )),
+                ),
+                Ok(_) => (
+                    "Declined the transfer.".to_string(),
+                    Some((
+                        from_discord_id,
+                        format!(
+                            "{}#{} declined your transfer.",
+                            interaction.user.name, interaction.user.discriminator
+                        ),
+                    )),
+                ),
+                Err(e) => (format!("Could not transfer account(s):\n{}", e), None),
+            }
+        }
+    };
+
+    interaction
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|msg| {
+                    msg.content(content)
+                        .set_embeds(Vec::new())
+                        .set_components(CreateComponents::default())
+                })
+        })
+        .await
+        .unwrap();
+
+    if let Some((from_discord_id, notice)) = notice {
+        let success = match UserId(from_discord_id as u64).create_dm_channel(&ctx).await {
+            Ok(channel) => channel
+                .send_message(&ctx.http, |msg| msg.content(notice))
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = success {
+            println!(
+                "Error sending transfer notice to `{}`:\n {}",
+                from_discord_id, error
+            );
+        }
+    }
+}
+
+/// Re-points the links of a transfer to the recipient in one transaction, returning the names the
+/// sender knew the transferred accounts by. Accounts the sender unlinked in the meantime are skipped.
+async fn complete(
+    database: &sqlx::SqlitePool,
+    transfer_id: i64,
+    from_discord_id: i64,
+    to_discord_id: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    let accounts = sqlx::query("SELECT transfer_accounts.genshin_uid, alias FROM transfer_accounts \
+                                INNER JOIN users ON transfer_accounts.genshin_uid = users.genshin_uid \
+                                WHERE transfer_id = ? AND discord_id = ? AND role = 'owner';")
+        .bind(transfer_id)
+        .bind(from_discord_id)
+        .fetch_all(&mut tx)
+        .await?;
+
+    for account in &accounts {
+        let genshin_uid: String = account.get("genshin_uid");
+
+        // The recipient may have the account linked already, which then becomes theirs
+        sqlx::query("INSERT INTO users (discord_id, hoyo_cookie_id, genshin_uid, role) \
+                     SELECT ?, hoyo_cookie_id, genshin_uid, 'owner' FROM users WHERE (discord_id, genshin_uid) = (?, ?) \
+                     ON CONFLICT (discord_id, genshin_uid) DO UPDATE SET hoyo_cookie_id = excluded.hoyo_cookie_id, role = 'owner';")
+            .bind(to_discord_id)
+            .bind(from_discord_id)
+            .bind(&genshin_uid)
+            .execute(&mut tx)
+            .await?;
+
+        sqlx::query("DELETE FROM users WHERE (discord_id, genshin_uid) = (?, ?);")
+            .bind(from_discord_id)
+            .bind(&genshin_uid)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM transfers WHERE transfer_id = ?;")
+        .bind(transfer_id)
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(accounts
+        .iter()
+        .map(|account| {
+            alias::name(
+                &account.get::<String, _>("genshin_uid"),
+                account.get::<Option<String>, _>("alias").as_deref(),
+            )
+        })
+        .collect())
+}
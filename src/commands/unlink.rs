@@ -1,8 +1,7 @@
 use std::sync::Arc;
-use std::time::Duration;
 
-use serenity::builder::{CreateApplicationCommand, CreateSelectMenuOption};
-use serenity::futures::StreamExt;
+use serenity::async_trait;
+use serenity::builder::{CreateApplicationCommand, CreateComponents, CreateSelectMenuOption};
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::prelude::component::ButtonStyle;
 use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
@@ -11,24 +10,23 @@ use serenity::model::Timestamp;
 use serenity::prelude::Context;
 use sqlx::Row;
 
+use crate::alias;
+use crate::trash;
+use crate::wizard::{Flow, Page, Stop, Transition, Wizard};
+
 pub async fn run(
     database: &sqlx::SqlitePool,
     command: &ApplicationCommandInteraction,
     ctx: Arc<Context>,
 ) {
-    let discord_id = command.user.id.0.to_string();
+    let discord_id = command.user.id.0 as i64;
 
-    let usercount = sqlx::query(
-        format!(
-            "SELECT COUNT(*) FROM users WHERE discord_id = \"{}\";",
-            discord_id
-        )
-        .as_str(),
-    )
-    .fetch_one(database)
-    .await
-    .unwrap()
-    .get::<u32, _>(0);
+    let usercount = sqlx::query("SELECT COUNT(*) FROM users WHERE discord_id = ?;")
+        .bind(discord_id)
+        .fetch_one(database)
+        .await
+        .unwrap()
+        .get::<u32, _>(0);
 
     if usercount == 0 {
         command
@@ -51,7 +49,7 @@ pub async fn run(
                         e.description("Are you sure you want to unlink an account?")
                             .colour((122, 71, 72))
                             .footer(|f| {
-                                f.icon_url(command.user.avatar_url().unwrap()).text(format!(
+                                f.icon_url(command.user.face()).text(format!(
                                     "Requested by {}#{}",
                                     command.user.name, command.user.discriminator
                                 ))
@@ -63,41 +61,13 @@ pub async fn run(
         .await
         .unwrap();
 
-    let msg = command
-        .user
-        .direct_message(&ctx.http, |msg| {
-            msg.components(|comp| {
-                comp.create_action_row(|row| {
-                    row.create_button(|btn| {
-                        btn.custom_id("cancel")
-                            .label("Cancel")
-                            .style(ButtonStyle::Secondary)
-                    })
-                    .create_button(|btn| {
-                        btn.custom_id("proceed")
-                            .label("Unlink")
-                            .style(ButtonStyle::Success)
-                    })
-                })
-            })
-        })
+    Wizard::start(ctx, "unlink", &command.user, None)
         .await
-        .unwrap();
-
-    let mut interaction_stream = msg
-        .await_component_interactions(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .build();
-
-    while let Some(interaction) = interaction_stream.next().await {
-        let action = &interaction.data.custom_id;
-
-        msg.delete(&ctx).await.unwrap();
-
-        if action == "proceed" {
-            unlink(database, interaction, ctx.clone()).await;
-        }
-    }
+        .run(
+            &mut UnlinkFlow::new(database, discord_id),
+            UnlinkStep::Start,
+        )
+        .await;
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -111,227 +81,249 @@ pub async fn unlink(
     interaction: Arc<MessageComponentInteraction>,
     ctx: Arc<Context>,
 ) {
-    let discord_id = interaction.user.id.0.to_string();
+    let discord_id = interaction.user.id.0 as i64;
+    let user = interaction.user.clone();
 
-    let users = sqlx::query(
-        format!(
-            "SELECT genshin_uid FROM users WHERE discord_id = {};",
-            discord_id
+    Wizard::start(ctx, "unlink", &user, Some(interaction))
+        .await
+        .run(
+            &mut UnlinkFlow::new(database, discord_id),
+            UnlinkStep::Select,
         )
-        .as_str(),
-    )
-    .fetch_all(database)
-    .await
-    .unwrap();
-
-    let users = users
-        .into_iter()
-        .map::<String, _>(|user| user.get(0))
-        .collect::<Vec<String>>();
+        .await;
+}
 
-    interaction
-        .create_interaction_response(&ctx, |res| {
-            res.kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|msg| {
-                    msg.embed(|e| {
-                        e.description("Please select an account to unlink")
-                            .colour((122, 71, 72))
-                    })
-                })
-        })
-        .await
-        .unwrap();
+#[derive(Clone)]
+enum UnlinkStep {
+    Start,
+    Select,
+    Confirm(Vec<String>),
+}
 
-    let msg = interaction
-        .user
-        .direct_message(&ctx, |msg| {
-            msg.components(|comp| {
-                comp.create_action_row(|row| {
-                    row.create_select_menu(|menu| {
-                        menu.custom_id("account")
-                            .placeholder("Select UID")
-                            .options(|select| {
-                                let mut options = Vec::new();
-
-                                for user in &users {
-                                    options.push(CreateSelectMenuOption::new(user, user));
-                                }
-
-                                select.set_options(options)
-                            })
-                    })
-                })
-            })
-        })
-        .await
-        .unwrap();
+struct UnlinkFlow<'a> {
+    database: &'a sqlx::SqlitePool,
+    discord_id: i64,
+}
 
-    let interaction = match msg
-        .await_component_interaction(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .await
-    {
-        Some(val) => {
-            msg.delete(&ctx).await.unwrap();
+impl<'a> UnlinkFlow<'a> {
+    fn new(database: &'a sqlx::SqlitePool, discord_id: i64) -> UnlinkFlow<'a> {
+        UnlinkFlow {
+            database,
+            discord_id,
+        }
+    }
+}
 
-            if &interaction.data.custom_id == "cancel" {
-                return;
+#[async_trait]
+impl Flow for UnlinkFlow<'_> {
+    type Step = UnlinkStep;
+
+    async fn step(
+        &mut self,
+        wizard: &mut Wizard,
+        step: UnlinkStep,
+    ) -> Result<Transition<UnlinkStep>, Stop> {
+        match step {
+            UnlinkStep::Start => {
+                wizard
+                    .ask(Page::default().button("proceed", "Unlink", ButtonStyle::Success))
+                    .await?;
+
+                Ok(Transition::Next(UnlinkStep::Select))
             }
+            UnlinkStep::Select => {
+                // Viewers can unlink the accounts shared with them, which leaves the owner's link as is
+                let users =
+                    sqlx::query("SELECT genshin_uid, alias FROM users WHERE discord_id = ?;")
+                        .bind(self.discord_id)
+                        .fetch_all(self.database)
+                        .await
+                        .unwrap();
 
-            val
-        }
-        None => {
-            msg.reply(&ctx, "Timed out").await.unwrap();
-            msg.delete(&ctx).await.unwrap();
-            return;
-        }
-    };
+                // The buttons of an old `/accounts` message can outlive the accounts
+                if users.is_empty() {
+                    wizard.say("You have no linked accounts").await;
 
-    let genshin_uid = &interaction.data.values[0];
+                    return Ok(Transition::Done);
+                }
 
-    interaction
-        .create_interaction_response(&ctx, |res| {
-            res.kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|msg| {
-                    msg.embed(|e| {
-                        e.description(format!(
-                            "Your account with uid: `{}` will be unlinked. Proceed?",
-                            genshin_uid
-                        ))
-                        .colour((122, 71, 72))
-                    })
-                })
-        })
-        .await
-        .unwrap();
+                // A select menu holds at most 25 options, one of which unlinks everything
+                let mut options = users
+                    .iter()
+                    .take(24)
+                    .map(alias::option)
+                    .collect::<Vec<CreateSelectMenuOption>>();
 
-    let msg = interaction
-        .user
-        .direct_message(&ctx.http, |msg| {
-            msg.components(|comp| {
-                comp.create_action_row(|row| {
-                    row.create_button(|btn| {
-                        btn.custom_id("cancel")
-                            .label("Cancel")
-                            .style(ButtonStyle::Secondary)
-                    })
-                    .create_button(|btn| {
-                        btn.custom_id("proceed")
-                            .label("Unlink!")
-                            .style(ButtonStyle::Success)
-                    })
-                })
-            })
-        })
-        .await
-        .unwrap();
+                if users.len() > 1 {
+                    let mut everything = CreateSelectMenuOption::new("Unlink everything", "*");
+                    everything.description(format!("All {} linked accounts", users.len()));
 
-    let mut interaction_stream = msg
-        .await_component_interactions(&*ctx)
-        .timeout(Duration::from_secs(120))
-        .build();
-
-    while let Some(interaction) = interaction_stream.next().await {
-        let action = &interaction.data.custom_id;
-
-        msg.delete(&ctx).await.unwrap();
-
-        if action == "proceed" {
-            let count_config = sqlx::query(format!("SELECT COUNT(*) FROM users INNER JOIN config on users.genshin_uid = config.genshin_uid WHERE users.genshin_uid = \"{}\";", genshin_uid).as_str())
-                .fetch_one(database).await.unwrap().get::<u32, _>(0);
-
-            let hoyo_query = sqlx::query(format!("SELECT COUNT(*), cookie_id FROM users INNER JOIN hoyo_cookie on users.hoyo_cookie_id = hoyo_cookie.cookie_id WHERE genshin_uid = \"{}\";", genshin_uid).as_str())
-                .fetch_one(database).await.unwrap();
-
-            let count_hoyo_cookie: u32 = hoyo_query.get(0);
-
-            let query = sqlx::query(
-                format!(
-                    "DELETE FROM users WHERE (discord_id, genshin_uid) = (\"{}\", \"{}\");",
-                    discord_id, genshin_uid
-                )
-                .as_str(),
-            )
-            .execute(database)
-            .await;
-
-            if let Err(e) = query {
-                interaction
-                    .create_interaction_response(&ctx, |res| {
-                        res.kind(InteractionResponseType::ChannelMessageWithSource)
-                            .interaction_response_data(|msg| {
-                                msg.content(format!("Could not unlink account:\n{}", e))
-                            })
-                    })
-                    .await
-                    .unwrap();
+                    options.push(everything);
+                }
 
-                return;
+                let max_values = options.len() as u64;
+
+                let input = wizard
+                    .ask(Page::new("Please select the accounts to unlink").select(
+                        "Select UIDs",
+                        options,
+                        max_values,
+                    ))
+                    .await?;
+
+                let genshin_uids = if input.values.iter().any(|value| value == "*") {
+                    users
+                        .into_iter()
+                        .map::<String, _>(|user| user.get("genshin_uid"))
+                        .collect::<Vec<String>>()
+                } else {
+                    input.values
+                };
+
+                Ok(Transition::Next(UnlinkStep::Confirm(genshin_uids)))
             }
-
-            if count_config == 1 {
-                let query = sqlx::query(
-                    format!(
-                        "DELETE FROM config WHERE genshin_uid = \"{}\";",
-                        genshin_uid
+            UnlinkStep::Confirm(genshin_uids) => {
+                wizard
+                    .ask(
+                        Page::new(format!(
+                            "The following accounts will be unlinked:```\n{}```Proceed?",
+                            alias::names(self.database, self.discord_id, &genshin_uids)
+                                .await
+                                .join("\n")
+                        ))
+                        .button(
+                            "proceed",
+                            "Unlink!",
+                            ButtonStyle::Success,
+                        ),
                     )
-                    .as_str(),
-                )
-                .execute(database)
-                .await;
-
-                if let Err(e) = query {
-                    interaction
-                        .create_interaction_response(&ctx, |res| {
-                            res.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|msg| {
-                                    msg.content(format!("Could not unlink account:\n{}", e))
-                                })
-                        })
-                        .await
-                        .unwrap();
-
-                    return;
+                    .await?;
+
+                match remove(self.database, self.discord_id, &genshin_uids).await {
+                    Ok(batch_id) => {
+                        wizard
+                            .finish(
+                                Page::new(format!(
+                                    "Successfully unlinked {} account(s)! You can undo this within {} hour(s).",
+                                    genshin_uids.len(),
+                                    trash::grace_period().as_secs() / 3600
+                                ))
+                                .persistent_button(
+                                    &format!("undo_unlink:{}", batch_id),
+                                    "Undo",
+                                    ButtonStyle::Secondary,
+                                ),
+                            )
+                            .await
+                    }
+                    Err(e) => {
+                        wizard
+                            .say(format!("Could not unlink account(s):\n{}", e))
+                            .await
+                    }
                 }
+
+                Ok(Transition::Done)
             }
+        }
+    }
+}
 
-            if count_hoyo_cookie == 1 {
-                let cookie_id: u32 = hoyo_query.get(1);
+/// Moves the links to `unlinked_users` in one transaction, returning the batch they can be
+/// restored by. Their configs, cookies and viewers are kept until the grace period is over.
+async fn remove(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+    genshin_uids: &[String],
+) -> Result<i64, sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    let batch_id = sqlx::query("INSERT INTO unlink_batches DEFAULT VALUES RETURNING batch_id;")
+        .fetch_one(&mut tx)
+        .await?
+        .get::<i64, _>(0);
+
+    for genshin_uid in genshin_uids {
+        sqlx::query("INSERT INTO unlinked_users (batch_id, discord_id, hoyo_cookie_id, genshin_uid, role, alias, linked_at) \
+                     SELECT ?, discord_id, hoyo_cookie_id, genshin_uid, role, alias, created_at FROM users WHERE (discord_id, genshin_uid) = (?, ?);")
+            .bind(batch_id)
+            .bind(discord_id)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?;
+
+        sqlx::query("DELETE FROM users WHERE (discord_id, genshin_uid) = (?, ?);")
+            .bind(discord_id)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?;
+    }
 
-                let query = sqlx::query(
-                    format!(
-                        "DELETE FROM hoyo_cookie WHERE cookie_id = \"{}\";",
-                        cookie_id
-                    )
-                    .as_str(),
-                )
-                .execute(database)
-                .await;
-
-                if let Err(e) = query {
-                    interaction
-                        .create_interaction_response(&ctx, |res| {
-                            res.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|msg| {
-                                    msg.content(format!("Could not unlink account:\n{}", e))
-                                })
-                        })
-                        .await
-                        .unwrap();
+    tx.commit().await?;
 
-                    return;
-                }
-            }
+    Ok(batch_id)
+}
+
+/// Restores the accounts of an unlink, as long as its grace period is not over.
+pub async fn undo(
+    database: &sqlx::SqlitePool,
+    interaction: Arc<MessageComponentInteraction>,
+    ctx: Arc<Context>,
+    batch_id: i64,
+) {
+    let discord_id = interaction.user.id.0 as i64;
+
+    let restored = restore(database, discord_id, batch_id).await;
 
-            interaction
-                .create_interaction_response(&ctx, |res| {
-                    res.kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|msg| {
-                            msg.content("Successfully unlinked account!")
-                        })
+    let content = match restored {
+        Ok(None) => "This unlink can no longer be undone.".to_string(),
+        Ok(Some(count)) => format!("Successfully restored {} account(s)!", count),
+        Err(e) => format!("Could not restore account(s):\n{}", e),
+    };
+
+    interaction
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|msg| {
+                    msg.content(content)
+                        .set_embeds(Vec::new())
+                        .set_components(CreateComponents::default())
                 })
-                .await
-                .unwrap();
-        }
-    }
+        })
+        .await
+        .unwrap();
+}
+
+/// Restores the links of a batch in one transaction, returning how many accounts were restored,
+/// or `None` if the batch is gone.
+async fn restore(
+    database: &sqlx::SqlitePool,
+    discord_id: i64,
+    batch_id: i64,
+) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    // Accounts that were linked again in the meantime are left as they are, and an alias given to
+    // another account in the meantime stays with that account
+    let count = sqlx::query("INSERT INTO users (discord_id, hoyo_cookie_id, genshin_uid, role, alias, created_at) \
+                             SELECT discord_id, hoyo_cookie_id, genshin_uid, role, \
+                             CASE WHEN EXISTS (SELECT 1 FROM users WHERE users.discord_id = unlinked_users.discord_id AND users.alias = unlinked_users.alias COLLATE NOCASE) THEN NULL ELSE alias END, \
+                             linked_at FROM unlinked_users WHERE (batch_id, discord_id) = (?, ?) \
+                             ON CONFLICT (discord_id, genshin_uid) DO NOTHING;")
+        .bind(batch_id)
+        .bind(discord_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+    let unlinked = sqlx::query("DELETE FROM unlinked_users WHERE (batch_id, discord_id) = (?, ?);")
+        .bind(batch_id)
+        .bind(discord_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(if unlinked == 0 { None } else { Some(count) })
 }
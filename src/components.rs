@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::prelude::Context;
+
+use crate::commands;
+use crate::session::{self, Sessions};
+
+/// Handles components no collector is waiting for: the persistent `/accounts`, health check, undo
+/// and transfer buttons, and menus of flows that were lost in a restart.
+pub async fn handle(
+    database: &sqlx::SqlitePool,
+    component: Arc<MessageComponentInteraction>,
+    ctx: Arc<Context>,
+) {
+    match component.data.custom_id.as_str() {
+        "accounts:link" => return commands::link::link(database, component, ctx).await,
+        "accounts:relink" | "refresh_token" => {
+            return commands::relink::relink(database, component, ctx).await
+        }
+        "accounts:unlink" => return commands::unlink::unlink(database, component, ctx).await,
+        _ => (),
+    }
+
+    if let Some(batch_id) = component.data.custom_id.strip_prefix("undo_unlink:") {
+        let batch_id = batch_id.parse::<i64>().unwrap();
+
+        return commands::unlink::undo(database, component, ctx, batch_id).await;
+    }
+
+    for (prefix, accept) in [("transfer_accept:", true), ("transfer_decline:", false)] {
+        if let Some(transfer_id) = component.data.custom_id.strip_prefix(prefix) {
+            let transfer_id = transfer_id.parse::<i64>().unwrap();
+
+            return commands::transfer::answer(database, component, ctx, transfer_id, accept).await;
+        }
+    }
+
+    let (flow, _, action) = match session::split_custom_id(&component.data.custom_id) {
+        Some(val) => val,
+        None => return,
+    };
+
+    if Sessions::started_here(&ctx, &component.data.custom_id).await {
+        return;
+    }
+
+    component.message.delete(&ctx).await.ok();
+
+    // Flows are started anew, as their progress did not survive
+    match (flow, action) {
+        (_, "cancel") => (),
+        ("link", _) => return commands::link::link(database, component, ctx).await,
+        ("relink", _) => return commands::relink::relink(database, component, ctx).await,
+        ("unlink", _) => return commands::unlink::unlink(database, component, ctx).await,
+        _ => (),
+    }
+
+    component
+        .create_interaction_response(&ctx, |res| {
+            res.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.content("This menu has expired."))
+        })
+        .await
+        .unwrap();
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serenity::http::Http;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::UserId;
+use sqlx::Row;
+
+use crate::alias;
+use crate::hoyolab::{self, Credentials};
+
+/// Time of day (UTC, in seconds) at which all credentials are checked.
+const CHECK_TIME: u64 = 3 * 60 * 60;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// How often a check put off by a HoYoLab outage is tried again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+pub async fn run(database: sqlx::SqlitePool, http: Arc<Http>) {
+    loop {
+        let time_of_day = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            % DAY;
+
+        let wait = match (CHECK_TIME + DAY - time_of_day) % DAY {
+            0 => DAY,
+            wait => wait,
+        };
+
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+
+        // Put off while HoYoLab is down, each attempt probing whether it is back
+        while !check_all(&database, &http).await {
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+}
+
+/// Validates every stored cookie against HoYoLab, and notifies the owners of cookies that died.
+/// Returns `false` if HoYoLab went down before all cookies were checked.
+pub async fn check_all(database: &sqlx::SqlitePool, http: &Http) -> bool {
+    let cookies = sqlx::query("SELECT cookie_id, ltuid, ltoken, cookie_token, account_id, lang, version, account_mid, status FROM hoyo_cookie;")
+        .fetch_all(database)
+        .await
+        .unwrap();
+
+    for cookie in cookies {
+        let cookie_id: u32 = cookie.get("cookie_id");
+        let previous_status: String = cookie.get("status");
+
+        let hoyo_client = hoyolab::Client::new(Credentials::from_row(&cookie));
+
+        let status = match hoyo_client.game_roles().await {
+            Ok(_) => "healthy",
+            Err(error) if error.is_auth_failure() => "dead",
+            Err(hoyolab::Error::Unavailable) => return false,
+            Err(error) => {
+                // Network trouble says nothing about the cookie itself
+                println!("Could not verify cookie {}:\n {}", cookie_id, error);
+                continue;
+            }
+        };
+
+        sqlx::query("UPDATE hoyo_cookie SET status = ?, last_verified_at = CURRENT_TIMESTAMP WHERE cookie_id = ?;")
+            .bind(status)
+            .bind(cookie_id)
+            .execute(database)
+            .await
+            .unwrap();
+
+        if status == "dead" && previous_status != "dead" {
+            notify_dead(database, http, cookie_id).await;
+        }
+    }
+
+    true
+}
+
+async fn notify_dead(database: &sqlx::SqlitePool, http: &Http, cookie_id: u32) {
+    // Only owners can update the token
+    let users = sqlx::query(
+        "SELECT discord_id, genshin_uid, alias FROM users WHERE hoyo_cookie_id = ? AND role = 'owner';",
+    )
+    .bind(cookie_id)
+    .fetch_all(database)
+    .await
+    .unwrap();
+
+    let mut discord_ids = users
+        .iter()
+        .map::<i64, _>(|user| user.get("discord_id"))
+        .collect::<Vec<i64>>();
+    discord_ids.sort();
+    discord_ids.dedup();
+
+    for discord_id in discord_ids {
+        let genshin_uids = users
+            .iter()
+            .filter(|user| user.get::<i64, _>("discord_id") == discord_id)
+            .map(|user| {
+                alias::name(
+                    &user.get::<String, _>("genshin_uid"),
+                    user.get::<Option<String>, _>("alias").as_deref(),
+                )
+            })
+            .collect::<Vec<String>>();
+
+        let channel = match UserId(discord_id as u64).create_dm_channel(http).await {
+            Ok(channel) => channel,
+            Err(error) => {
+                println!(
+                    "Error sending expiry notice to `{}`:\n {}",
+                    discord_id, error
+                );
+                continue;
+            }
+        };
+
+        let success = channel
+            .send_message(http, |msg| {
+                msg.embed(|e| {
+                    e.title("HoYoLab token expired")
+                        .description(format!(
+                            "The HoYoLab token of your account(s) {} no longer works, so they will be skipped when claiming codes automatically. Press the button below to update it.",
+                            genshin_uids.join(", ")
+                        ))
+                        .colour((122, 71, 72))
+                })
+                .components(|comp| {
+                    comp.create_action_row(|row| {
+                        row.create_button(|btn| {
+                            btn.custom_id("refresh_token")
+                                .label("Update token")
+                                .style(ButtonStyle::Primary)
+                        })
+                    })
+                })
+            })
+            .await;
+
+        if let Err(error) = success {
+            println!(
+                "Error sending expiry notice to `{}`:\n {}",
+                discord_id, error
+            );
+        }
+    }
+}
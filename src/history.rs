@@ -0,0 +1,29 @@
+use sqlx::SqlitePool;
+
+use crate::hoyolab::ClaimOutcome;
+
+/// Records the outcome of a claim. `code` is left out for daily rewards.
+pub async fn record(
+    database: &SqlitePool,
+    genshin_uid: &str,
+    code: Option<&str>,
+    outcome: &ClaimOutcome,
+    attempts: u32,
+) {
+    let success = sqlx::query(
+        "INSERT INTO claim_history (genshin_uid, kind, code, outcome, error, attempts) VALUES (?, ?, ?, ?, ?, ?);",
+    )
+    .bind(genshin_uid)
+    .bind(if code.is_some() { "code" } else { "daily" })
+    .bind(code)
+    .bind(outcome.name())
+    .bind(outcome.error())
+    .bind(attempts)
+    .execute(database)
+    .await;
+
+    // Losing a statistic is no reason to fail the claim itself
+    if let Err(error) = success {
+        println!("Error recording claim on `{}`:\n {}", genshin_uid, error);
+    }
+}
@@ -0,0 +1,227 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::Error;
+
+/// Consecutive failed requests after which HoYoLab is considered down.
+const THRESHOLD: u32 = 5;
+
+/// How long requests are short-circuited before one is let through to probe HoYoLab.
+const COOLDOWN: Duration = Duration::from_secs(60);
+
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// A probe request is in flight, everything else is still short-circuited
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+/// Tracks whether HoYoLab is reachable, short-circuiting requests while it is not.
+pub struct Breaker {
+    state: Mutex<State>,
+}
+
+// Clients are created per account, so the breaker is shared by all of them
+static BREAKER: Breaker = Breaker::new();
+
+/// Whether requests to HoYoLab currently go through, or at least a probe would.
+pub fn is_available() -> bool {
+    BREAKER.is_available()
+}
+
+/// Sends the request unless the breaker is open, keeping track of whether HoYoLab is reachable.
+pub async fn guard<T>(request: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+    BREAKER.guard(request).await
+}
+
+impl Breaker {
+    pub const fn new() -> Breaker {
+        Breaker {
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => true,
+            State::Open { since } | State::HalfOpen { since } => since.elapsed() >= COOLDOWN,
+        }
+    }
+
+    pub async fn guard<T>(
+        &self,
+        request: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        if !self.try_acquire() {
+            return Err(Error::Unavailable);
+        }
+
+        let result = request.await;
+        self.record(matches!(&result, Err(error) if error.is_outage()));
+
+        result
+    }
+
+    /// Whether a request may be sent, letting it through as the probe once the cooldown is over.
+    fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        match *state {
+            State::Closed { .. } => true,
+            // A probe that never finished, e.g. because its task was dropped, does not count
+            State::Open { since } | State::HalfOpen { since } if since.elapsed() >= COOLDOWN => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records whether a request that was let through found HoYoLab down.
+    fn record(&self, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+
+        *state = match (&*state, failed) {
+            (_, false) => State::Closed { failures: 0 },
+            (State::Closed { failures }, true) if failures + 1 < THRESHOLD => State::Closed {
+                failures: failures + 1,
+            },
+            (State::Open { since }, true) => State::Open { since: *since },
+            (_, true) => {
+                println!("HoYoLab appears to be down, pausing requests");
+
+                State::Open {
+                    since: Instant::now(),
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Breaker {
+        let breaker = Breaker::new();
+
+        for _ in 0..THRESHOLD {
+            assert!(breaker.try_acquire());
+            breaker.record(true);
+        }
+
+        breaker
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn opens_after_threshold_failures() {
+        let breaker = Breaker::new();
+
+        for _ in 0..THRESHOLD - 1 {
+            assert!(breaker.try_acquire());
+            breaker.record(true);
+        }
+
+        assert!(breaker.is_available());
+
+        breaker.record(true);
+
+        assert!(!breaker.is_available());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn success_resets_the_failures() {
+        let breaker = Breaker::new();
+
+        for _ in 0..THRESHOLD - 1 {
+            breaker.record(true);
+        }
+
+        breaker.record(false);
+        breaker.record(true);
+
+        assert!(breaker.is_available());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn short_circuits_while_open() {
+        let breaker = open();
+        let mut sent = false;
+
+        let result = breaker
+            .guard(async {
+                sent = true;
+
+                Ok(())
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Unavailable)));
+        assert!(!sent);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lets_one_probe_through_after_cooldown() {
+        let breaker = open();
+
+        tokio::time::advance(COOLDOWN).await;
+
+        assert!(breaker.is_available());
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn closes_when_the_probe_succeeds() {
+        let breaker = open();
+
+        tokio::time::advance(COOLDOWN).await;
+
+        assert!(breaker.try_acquire());
+        breaker.record(false);
+
+        assert!(breaker.is_available());
+        assert!(breaker.try_acquire());
+        assert!(breaker.try_acquire());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reopens_when_the_probe_fails() {
+        let breaker = open();
+
+        tokio::time::advance(COOLDOWN).await;
+
+        assert!(breaker.try_acquire());
+        breaker.record(true);
+
+        assert!(!breaker.is_available());
+        assert!(!breaker.try_acquire());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replaces_a_stale_probe() {
+        let breaker = open();
+
+        tokio::time::advance(COOLDOWN).await;
+
+        // The probe never reports back
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+
+        tokio::time::advance(COOLDOWN).await;
+
+        assert!(breaker.try_acquire());
+    }
+}
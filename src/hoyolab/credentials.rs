@@ -0,0 +1,232 @@
+use std::fmt;
+
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+use super::export;
+
+/// Layout of the HoYoLab cookie the credentials were taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CookieVersion {
+    /// `ltuid`, `ltoken`, `cookie_token` and `account_id`
+    V1 = 1,
+    /// `ltuid_v2`, `ltoken_v2`, `cookie_token_v2` and `account_id_v2`, plus the `ltmid_v2` they are bound to
+    V2 = 2,
+}
+
+impl fmt::Display for CookieVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieVersion::V1 => write!(f, "v1"),
+            CookieVersion::V2 => write!(f, "v2"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub version: CookieVersion,
+    pub ltuid: String,
+    pub ltoken: String,
+    pub cookie_token: String,
+    pub account_id: String,
+    pub account_mid: Option<String>,
+    pub lang: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CookieError {
+    Missing(&'static str),
+    InvalidExport(String),
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieError::Missing(key) => write!(f, "the cookie does not contain `{}`", key),
+            CookieError::InvalidExport(error) => {
+                write!(f, "the cookie export could not be read: {}", error)
+            }
+        }
+    }
+}
+
+impl Credentials {
+    /// Reads credentials from a cookie string or browser cookie export, detecting whether it uses
+    /// the v1 or v2 layout.
+    pub fn parse(cookie: &str) -> Result<Credentials, CookieError> {
+        let fields = export::cookie_fields(cookie)?;
+
+        let version = if fields.contains_key("ltoken_v2") {
+            CookieVersion::V2
+        } else {
+            CookieVersion::V1
+        };
+
+        let get = |key: &'static str| -> Result<String, CookieError> {
+            let key = match version {
+                CookieVersion::V1 => key,
+                CookieVersion::V2 => match key {
+                    "ltuid" => "ltuid_v2",
+                    "ltoken" => "ltoken_v2",
+                    "cookie_token" => "cookie_token_v2",
+                    "account_id" => "account_id_v2",
+                    key => key,
+                },
+            };
+
+            fields
+                .get(key)
+                .map(|value| value.to_string())
+                .ok_or(CookieError::Missing(key))
+        };
+
+        let account_mid = fields
+            .get("ltmid_v2")
+            .or_else(|| fields.get("account_mid_v2"))
+            .map(|value| value.to_string());
+
+        if version == CookieVersion::V2 && account_mid.is_none() {
+            return Err(CookieError::Missing("ltmid_v2"));
+        }
+
+        Ok(Credentials {
+            version,
+            ltuid: get("ltuid")?,
+            ltoken: get("ltoken")?,
+            cookie_token: get("cookie_token")?,
+            account_id: get("account_id")?,
+            account_mid,
+            lang: fields
+                .get("mi18nLang")
+                .map(String::as_str)
+                .unwrap_or("en-us")
+                .to_string(),
+        })
+    }
+
+    /// Reads credentials from a query that selects the `hoyo_cookie` columns by name.
+    pub fn from_row(row: &SqliteRow) -> Credentials {
+        Credentials {
+            version: match row.get::<u32, _>("version") {
+                2 => CookieVersion::V2,
+                _ => CookieVersion::V1,
+            },
+            ltuid: row.get("ltuid"),
+            ltoken: row.get("ltoken"),
+            cookie_token: row.get("cookie_token"),
+            account_id: row.get("account_id"),
+            account_mid: row.get("account_mid"),
+            lang: row.get("lang"),
+        }
+    }
+
+    /// Rebuilds the cookie header HoYoLab expects for this cookie version.
+    pub fn header(&self) -> String {
+        let mut fields = match self.version {
+            CookieVersion::V1 => vec![
+                ("ltuid", self.ltuid.as_str()),
+                ("ltoken", self.ltoken.as_str()),
+                ("cookie_token", self.cookie_token.as_str()),
+                ("account_id", self.account_id.as_str()),
+            ],
+            CookieVersion::V2 => vec![
+                ("ltuid_v2", self.ltuid.as_str()),
+                ("ltoken_v2", self.ltoken.as_str()),
+                ("cookie_token_v2", self.cookie_token.as_str()),
+                ("account_id_v2", self.account_id.as_str()),
+            ],
+        };
+
+        if let Some(account_mid) = &self.account_mid {
+            fields.push(("ltmid_v2", account_mid));
+            fields.push(("account_mid_v2", account_mid));
+        }
+
+        fields.push(("mi18nLang", self.lang.as_str()));
+
+        fields
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v1_cookies() {
+        let credentials = Credentials::parse(
+            "ltuid=1; ltoken=token; cookie_token=cookie; account_id=1; mi18nLang=de-de",
+        )
+        .unwrap();
+
+        assert_eq!(credentials.version, CookieVersion::V1);
+        assert_eq!(credentials.ltuid, "1");
+        assert_eq!(credentials.ltoken, "token");
+        assert_eq!(credentials.cookie_token, "cookie");
+        assert_eq!(credentials.account_id, "1");
+        assert_eq!(credentials.account_mid, None);
+        assert_eq!(credentials.lang, "de-de");
+    }
+
+    #[test]
+    fn parses_v2_cookies() {
+        let credentials = Credentials::parse(
+            "ltuid_v2=2; ltoken_v2=token; cookie_token_v2=cookie; account_id_v2=2; ltmid_v2=mid",
+        )
+        .unwrap();
+
+        assert_eq!(credentials.version, CookieVersion::V2);
+        assert_eq!(credentials.ltuid, "2");
+        assert_eq!(credentials.ltoken, "token");
+        assert_eq!(credentials.cookie_token, "cookie");
+        assert_eq!(credentials.account_id, "2");
+        assert_eq!(credentials.account_mid.as_deref(), Some("mid"));
+        assert_eq!(credentials.lang, "en-us");
+    }
+
+    #[test]
+    fn accepts_account_mid_v2_in_place_of_ltmid_v2() {
+        let credentials = Credentials::parse(
+            "ltuid_v2=2; ltoken_v2=token; cookie_token_v2=cookie; account_id_v2=2; account_mid_v2=mid",
+        )
+        .unwrap();
+
+        assert_eq!(credentials.account_mid.as_deref(), Some("mid"));
+    }
+
+    #[test]
+    fn rejects_v2_cookies_without_ltmid_v2() {
+        let error = Credentials::parse(
+            "ltuid_v2=2; ltoken_v2=token; cookie_token_v2=cookie; account_id_v2=2",
+        )
+        .unwrap_err();
+
+        assert_eq!(error, CookieError::Missing("ltmid_v2"));
+    }
+
+    #[test]
+    fn reports_the_missing_field() {
+        let v1 = Credentials::parse("ltuid=1; ltoken=token; account_id=1").unwrap_err();
+        let v2 = Credentials::parse("ltoken_v2=token; ltmid_v2=mid").unwrap_err();
+
+        assert_eq!(v1, CookieError::Missing("cookie_token"));
+        assert_eq!(v2, CookieError::Missing("ltuid_v2"));
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let cookie =
+            "ltuid_v2=2; ltoken_v2=token; cookie_token_v2=cookie; account_id_v2=2; ltmid_v2=mid";
+        let credentials = Credentials::parse(cookie).unwrap();
+        let reparsed = Credentials::parse(&credentials.header()).unwrap();
+
+        assert_eq!(reparsed.version, credentials.version);
+        assert_eq!(reparsed.ltoken, credentials.ltoken);
+        assert_eq!(reparsed.account_mid, credentials.account_mid);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::credentials::CookieError;
+
+/// A cookie as exported by browser extensions such as EditThisCookie and Cookie-Editor.
+#[derive(Deserialize)]
+struct ExportedCookie {
+    name: String,
+    value: String,
+    #[serde(default)]
+    domain: String,
+}
+
+/// Reads the cookie fields out of whatever the user pasted or uploaded: a
+/// `document.cookie` string, a JSON cookie export or a Netscape `cookies.txt` file.
+pub fn cookie_fields(cookie: &str) -> Result<HashMap<String, String>, CookieError> {
+    let cookie = cookie.trim();
+
+    let fields = if cookie.starts_with('[') {
+        serde_json::from_str::<Vec<ExportedCookie>>(cookie)
+            .map_err(|error| CookieError::InvalidExport(error.to_string()))?
+            .into_iter()
+            .filter(|cookie| is_hoyo_domain(&cookie.domain))
+            .map(|cookie| (cookie.name, cookie.value))
+            .collect()
+    } else if is_netscape(cookie) {
+        cookie
+            .lines()
+            .map(|line| line.trim_start_matches("#HttpOnly_"))
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let columns = line.split('\t').collect::<Vec<&str>>();
+
+                match columns.as_slice() {
+                    [domain, _, _, _, _, name, value] if is_hoyo_domain(domain) => {
+                        Some((name.to_string(), value.trim().to_string()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    } else {
+        cookie
+            .split(';')
+            .filter_map(|field| field.trim().split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect()
+    };
+
+    Ok(fields)
+}
+
+fn is_netscape(cookie: &str) -> bool {
+    cookie.starts_with("# Netscape HTTP Cookie File")
+        || cookie.lines().any(|line| line.split('\t').count() == 7)
+}
+
+/// Exports may contain cookies of other sites, which could shadow the HoYoLab ones.
+fn is_hoyo_domain(domain: &str) -> bool {
+    domain.is_empty() || domain.contains("hoyolab.com") || domain.contains("hoyoverse.com")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_document_cookie_strings() {
+        let fields = cookie_fields(" ltuid=1; ltoken = token ;mi18nLang=en-us ").unwrap();
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields["ltuid"], "1");
+        assert_eq!(fields["ltoken"], "token");
+        assert_eq!(fields["mi18nLang"], "en-us");
+    }
+
+    #[test]
+    fn reads_json_exports() {
+        let fields = cookie_fields(
+            r#"[
+                {"name": "ltuid", "value": "1", "domain": ".hoyolab.com"},
+                {"name": "ltoken", "value": "token", "domain": ".hoyoverse.com"},
+                {"name": "cookie_token", "value": "cookie"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields["ltuid"], "1");
+        assert_eq!(fields["ltoken"], "token");
+        assert_eq!(fields["cookie_token"], "cookie");
+    }
+
+    #[test]
+    fn rejects_malformed_json_exports() {
+        assert!(matches!(
+            cookie_fields(r#"[{"name": "ltuid"}]"#),
+            Err(CookieError::InvalidExport(_))
+        ));
+    }
+
+    #[test]
+    fn reads_cookies_txt() {
+        let fields = cookie_fields(
+            "# Netscape HTTP Cookie File\n\
+             .hoyolab.com\tTRUE\t/\tTRUE\t0\tltuid\t1\n\
+             #HttpOnly_.hoyolab.com\tTRUE\t/\tTRUE\t0\tltoken\ttoken\r\n\
+             # a comment\n",
+        )
+        .unwrap();
+
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["ltuid"], "1");
+        assert_eq!(fields["ltoken"], "token");
+    }
+
+    #[test]
+    fn ignores_cookies_of_other_sites() {
+        let json = cookie_fields(
+            r#"[
+                {"name": "ltuid", "value": "1", "domain": ".hoyolab.com"},
+                {"name": "ltuid", "value": "2", "domain": ".example.com"}
+            ]"#,
+        )
+        .unwrap();
+        let txt = cookie_fields(
+            ".example.com\tTRUE\t/\tTRUE\t0\tltuid\t2\n\
+             .hoyolab.com\tTRUE\t/\tTRUE\t0\tltuid\t1\n\
+             .example.com\tTRUE\t/\tTRUE\t0\tltoken\tforeign",
+        )
+        .unwrap();
+
+        assert_eq!(json["ltuid"], "1");
+        assert_eq!(txt["ltuid"], "1");
+        assert!(!txt.contains_key("ltoken"));
+    }
+}
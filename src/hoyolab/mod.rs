@@ -0,0 +1,190 @@
+mod breaker;
+mod credentials;
+mod export;
+mod outcome;
+mod retry;
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+pub use breaker::is_available;
+pub use credentials::Credentials;
+pub use outcome::ClaimOutcome;
+pub use retry::with_retry;
+
+const GAME_ROLES_URL: &str =
+    "https://api-os-takumi.hoyoverse.com/binding/api/getUserGameRolesByCookie";
+const DAILY_SIGN_URL: &str = "https://sg-hk4e-api.hoyolab.com/event/sol/sign";
+const REDEEM_CODE_URL: &str =
+    "https://sg-hk4e-api.hoyoverse.com/common/apicdkey/api/webExchangeCdkey";
+
+const DAILY_ACT_ID: &str = "e202102251931481";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct Response<T> {
+    retcode: i64,
+    message: String,
+    data: Option<T>,
+}
+
+impl<T> Response<T> {
+    fn into_result(self) -> Result<Option<T>, Error> {
+        if self.retcode == 0 {
+            Ok(self.data)
+        } else {
+            Err(Error::Api {
+                retcode: self.retcode,
+                message: self.message,
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GameRoleList {
+    list: Vec<GameRole>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GameRole {
+    pub game_uid: String,
+    pub nickname: String,
+    pub level: u32,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Api {
+        retcode: i64,
+        message: String,
+    },
+    /// Short-circuited by the breaker, without sending the request
+    Unavailable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(error) => write!(f, "could not reach HoYoLab: {}", error),
+            Error::Api { retcode, message } => {
+                write!(f, "HoYoLab returned {} ({})", message, retcode)
+            }
+            Error::Unavailable => write!(f, "HoYoLab appears to be down, try again later"),
+        }
+    }
+}
+
+impl Error {
+    /// Whether HoYoLab rejected the cookie itself, rather than the request failing.
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(
+            self,
+            Error::Api {
+                retcode: -100 | -1071 | 10001,
+                ..
+            }
+        )
+    }
+
+    /// Whether HoYoLab could not be reached or failed on its end, rather than refusing the request.
+    pub fn is_outage(&self) -> bool {
+        match self {
+            Error::Request(error) => match error.status() {
+                Some(status) => status.is_server_error(),
+                None => !error.is_decode(),
+            },
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Request(error)
+    }
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    credentials: Credentials,
+}
+
+impl Client {
+    pub fn new(credentials: Credentials) -> Client {
+        Client {
+            http: reqwest::Client::builder().timeout(TIMEOUT).build().unwrap(),
+            credentials,
+        }
+    }
+
+    /// Fetches the Genshin accounts bound to the HoYoLab account.
+    ///
+    /// This is an authenticated call, so it doubles as a check that the cookie is still valid.
+    pub async fn game_roles(&self) -> Result<Vec<GameRole>, Error> {
+        let request = self
+            .http
+            .get(GAME_ROLES_URL)
+            .query(&[("game_biz", "hk4e_global")]);
+
+        Ok(self
+            .send::<GameRoleList>(request)
+            .await?
+            .map(|data| data.list)
+            .unwrap_or_default())
+    }
+
+    pub async fn claim_daily(&self) -> Result<(), Error> {
+        let request = self
+            .http
+            .post(DAILY_SIGN_URL)
+            .query(&[("lang", self.credentials.lang.as_str())])
+            .json(&serde_json::json!({ "act_id": DAILY_ACT_ID }));
+
+        self.send::<serde_json::Value>(request).await?;
+
+        Ok(())
+    }
+
+    pub async fn claim_code(
+        &self,
+        genshin_uid: &str,
+        region: &str,
+        code: &str,
+    ) -> Result<(), Error> {
+        let request = self.http.get(REDEEM_CODE_URL).query(&[
+            ("uid", genshin_uid),
+            ("region", region),
+            ("cdkey", code),
+            ("game_biz", "hk4e_global"),
+            ("lang", self.credentials.lang.as_str()),
+        ]);
+
+        self.send::<serde_json::Value>(request).await?;
+
+        Ok(())
+    }
+
+    /// Sends the request with the credentials through the breaker, returning the response's data.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Option<T>, Error> {
+        breaker::guard(async {
+            request
+                .header(reqwest::header::COOKIE, self.credentials.header())
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Response<T>>()
+                .await?
+                .into_result()
+        })
+        .await
+    }
+}
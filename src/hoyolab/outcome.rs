@@ -0,0 +1,193 @@
+use std::fmt;
+
+use super::Error;
+
+/// What came of claiming a daily reward or redemption code on one account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClaimOutcome {
+    Claimed,
+    /// The daily reward was claimed today already
+    AlreadyClaimed,
+    InvalidCode,
+    ExpiredCode,
+    /// The code was redeemed on this account before
+    RedeemedAlready,
+    /// HoYoLab rejected the cookie
+    AuthFailed,
+    RateLimited,
+    /// HoYoLab could not be reached or failed on its end, so trying again later may succeed
+    Transient(String),
+    /// HoYoLab appears to be down, so the claim was not attempted
+    Unavailable,
+    /// Any other error, which trying again will not fix
+    Failed(String),
+}
+
+impl ClaimOutcome {
+    /// The name the outcome is stored under in `claim_history`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClaimOutcome::Claimed => "claimed",
+            ClaimOutcome::AlreadyClaimed => "already_claimed",
+            ClaimOutcome::InvalidCode => "invalid_code",
+            ClaimOutcome::ExpiredCode => "expired_code",
+            ClaimOutcome::RedeemedAlready => "redeemed_already",
+            ClaimOutcome::AuthFailed => "auth_failed",
+            ClaimOutcome::RateLimited => "rate_limited",
+            ClaimOutcome::Transient(_) => "transient",
+            ClaimOutcome::Unavailable => "unavailable",
+            ClaimOutcome::Failed(_) => "failed",
+        }
+    }
+
+    /// Whether trying again later may turn the outcome into a success.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ClaimOutcome::RateLimited | ClaimOutcome::Transient(_))
+    }
+
+    /// The error the outcome was mapped from, if it carries one.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            ClaimOutcome::Transient(error) | ClaimOutcome::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Renders the outcome as a line of a claim report, e.g. `what` = "daily".
+    pub fn render(&self, what: &str, account: &str) -> String {
+        match self {
+            ClaimOutcome::Claimed => format!("Successfully claimed {} on {}", what, account),
+            ClaimOutcome::AlreadyClaimed | ClaimOutcome::RedeemedAlready => {
+                format!("Already claimed {} on {}", what, account)
+            }
+            _ => format!("Error claiming {} on {}: `{}`", what, account, self),
+        }
+    }
+}
+
+impl fmt::Display for ClaimOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClaimOutcome::Claimed => write!(f, "claimed"),
+            ClaimOutcome::AlreadyClaimed => write!(f, "already claimed today"),
+            ClaimOutcome::InvalidCode => write!(f, "the code is invalid"),
+            ClaimOutcome::ExpiredCode => write!(f, "the code has expired"),
+            ClaimOutcome::RedeemedAlready => write!(f, "the code was redeemed already"),
+            ClaimOutcome::AuthFailed => {
+                write!(
+                    f,
+                    "the HoYoLab cookie has expired, use /relink to update it"
+                )
+            }
+            ClaimOutcome::RateLimited => write!(f, "rate limited by HoYoLab, try again later"),
+            ClaimOutcome::Unavailable => write!(f, "{}", Error::Unavailable),
+            ClaimOutcome::Transient(error) | ClaimOutcome::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<Result<(), Error>> for ClaimOutcome {
+    fn from(result: Result<(), Error>) -> Self {
+        let error = match result {
+            Ok(()) => return ClaimOutcome::Claimed,
+            Err(error) => error,
+        };
+
+        if error.is_auth_failure() {
+            return ClaimOutcome::AuthFailed;
+        }
+
+        match &error {
+            Error::Api { retcode, .. } => match retcode {
+                -5003 => ClaimOutcome::AlreadyClaimed,
+                -2001 => ClaimOutcome::ExpiredCode,
+                -2003 | -2004 | -2006 => ClaimOutcome::InvalidCode,
+                -2017 | -2018 => ClaimOutcome::RedeemedAlready,
+                -110 | -2016 => ClaimOutcome::RateLimited,
+                _ => ClaimOutcome::Failed(error.to_string()),
+            },
+            Error::Request(request)
+                if request.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                ClaimOutcome::RateLimited
+            }
+            Error::Request(_) if error.is_outage() => ClaimOutcome::Transient(error.to_string()),
+            Error::Request(_) => ClaimOutcome::Failed(error.to_string()),
+            Error::Unavailable => ClaimOutcome::Unavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(retcode: i64) -> ClaimOutcome {
+        ClaimOutcome::from(Err(Error::Api {
+            retcode,
+            message: "message".to_string(),
+        }))
+    }
+
+    #[test]
+    fn maps_retcodes() {
+        assert_eq!(ClaimOutcome::from(Ok(())), ClaimOutcome::Claimed);
+        assert_eq!(api(-5003), ClaimOutcome::AlreadyClaimed);
+        assert_eq!(api(-2001), ClaimOutcome::ExpiredCode);
+        assert_eq!(api(-2003), ClaimOutcome::InvalidCode);
+        assert_eq!(api(-2004), ClaimOutcome::InvalidCode);
+        assert_eq!(api(-2006), ClaimOutcome::InvalidCode);
+        assert_eq!(api(-2017), ClaimOutcome::RedeemedAlready);
+        assert_eq!(api(-2018), ClaimOutcome::RedeemedAlready);
+        assert_eq!(api(-110), ClaimOutcome::RateLimited);
+        assert_eq!(api(-2016), ClaimOutcome::RateLimited);
+    }
+
+    #[test]
+    fn maps_auth_failures() {
+        for retcode in [-100, -1071, 10001] {
+            assert_eq!(api(retcode), ClaimOutcome::AuthFailed);
+        }
+    }
+
+    #[test]
+    fn keeps_the_error_of_unknown_retcodes() {
+        let outcome = api(-1);
+
+        assert_eq!(outcome.name(), "failed");
+        assert_eq!(outcome.error(), Some("HoYoLab returned message (-1)"));
+        assert!(!outcome.is_transient());
+    }
+
+    #[test]
+    fn maps_short_circuited_requests() {
+        let outcome = ClaimOutcome::from(Err(Error::Unavailable));
+
+        assert_eq!(outcome, ClaimOutcome::Unavailable);
+        assert!(!outcome.is_transient());
+    }
+
+    #[test]
+    fn only_retries_what_may_succeed_later() {
+        assert!(ClaimOutcome::RateLimited.is_transient());
+        assert!(ClaimOutcome::Transient("timeout".to_string()).is_transient());
+        assert!(!ClaimOutcome::AuthFailed.is_transient());
+        assert!(!ClaimOutcome::InvalidCode.is_transient());
+    }
+
+    #[test]
+    fn renders_report_lines() {
+        assert_eq!(
+            ClaimOutcome::Claimed.render("daily", "Main (800000001)"),
+            "Successfully claimed daily on Main (800000001)"
+        );
+        assert_eq!(
+            ClaimOutcome::RedeemedAlready.render("code `CODE`", "800000001"),
+            "Already claimed code `CODE` on 800000001"
+        );
+        assert_eq!(
+            ClaimOutcome::ExpiredCode.render("code `CODE`", "800000001"),
+            "Error claiming code `CODE` on 800000001: `the code has expired`"
+        );
+    }
+}
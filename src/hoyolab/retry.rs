@@ -0,0 +1,99 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use super::{ClaimOutcome, Error};
+
+/// How often a claim is attempted before giving up on it.
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(16);
+
+/// Runs a claim until it succeeds or fails for a reason trying again will not fix, waiting
+/// exponentially longer between attempts. Returns the outcome together with the number of attempts.
+pub async fn with_retry<F, Fut>(mut claim: F) -> (ClaimOutcome, u32)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        let outcome = ClaimOutcome::from(claim().await);
+
+        if !outcome.is_transient() || attempts == MAX_ATTEMPTS {
+            return (outcome, attempts);
+        }
+
+        tokio::time::sleep(backoff(attempts)).await;
+    }
+}
+
+/// Delay before the next attempt, with jitter so claims failing together do not retry together.
+fn backoff(attempts: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempts - 1))
+        .min(MAX_DELAY);
+
+    // A freshly seeded hasher is random enough for jitter, without pulling in a crate for it
+    let random = RandomState::new().build_hasher().finish();
+
+    delay / 2 + delay.mul_f64((random % 1000) as f64 / 2000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_within_jitter() {
+        for attempts in 1..=3 {
+            let delay = BASE_DELAY * 2u32.pow(attempts - 1);
+
+            for _ in 0..100 {
+                let backoff = backoff(attempts);
+
+                assert!(backoff >= delay / 2, "{:?} < {:?}", backoff, delay / 2);
+                assert!(backoff <= delay, "{:?} > {:?}", backoff, delay);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        for attempts in [5, 10, 32, u32::MAX] {
+            assert!(backoff(attempts) <= MAX_DELAY);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let (outcome, attempts) = with_retry(|| async {
+            Err(Error::Api {
+                retcode: -110,
+                message: "too many requests".to_string(),
+            })
+        })
+        .await;
+
+        assert_eq!(outcome, ClaimOutcome::RateLimited);
+        assert_eq!(attempts, MAX_ATTEMPTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_fast_on_auth_failures() {
+        let (outcome, attempts) = with_retry(|| async {
+            Err(Error::Api {
+                retcode: -100,
+                message: "not logged in".to_string(),
+            })
+        })
+        .await;
+
+        assert_eq!(outcome, ClaimOutcome::AuthFailed);
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use sqlx::Row;
+
+/// Rows that no longer fit together, e.g. after a link or unlink was interrupted before
+/// transactions were used.
+#[derive(Default)]
+pub struct Report {
+    /// Cookies no user links to, not even through an unlink that can be undone
+    pub orphaned_cookies: Vec<u32>,
+    /// Configs of accounts no user links to
+    pub orphaned_configs: Vec<String>,
+    /// HoYoLab accounts stored as several cookies, with the cookie ids
+    pub duplicate_cookies: Vec<(String, Vec<u32>)>,
+    /// Links to a config or cookie that does not exist
+    pub dangling_users: Vec<(i64, String)>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_cookies.is_empty()
+            && self.orphaned_configs.is_empty()
+            && self.duplicate_cookies.is_empty()
+            && self.dangling_users.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "No integrity issues found.");
+        }
+
+        for cookie_id in &self.orphaned_cookies {
+            writeln!(f, "Cookie {} is not used by any account", cookie_id)?;
+        }
+
+        for genshin_uid in &self.orphaned_configs {
+            writeln!(f, "Config of {} is not used by any account", genshin_uid)?;
+        }
+
+        for (ltuid, cookie_ids) in &self.duplicate_cookies {
+            writeln!(
+                f,
+                "HoYoLab account {} is stored as cookies {:?}",
+                ltuid, cookie_ids
+            )?;
+        }
+
+        for (discord_id, genshin_uid) in &self.dangling_users {
+            writeln!(
+                f,
+                "Account {} of {} links to a missing config or cookie",
+                genshin_uid, discord_id
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn check(database: &sqlx::SqlitePool) -> Report {
+    let orphaned_cookies = sqlx::query("SELECT cookie_id FROM hoyo_cookie WHERE NOT EXISTS (SELECT 1 FROM users WHERE hoyo_cookie_id = cookie_id) \
+                                        AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE hoyo_cookie_id = cookie_id);")
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map::<u32, _>(|row| row.get(0))
+        .collect::<Vec<u32>>();
+
+    let orphaned_configs = sqlx::query("SELECT genshin_uid FROM config WHERE NOT EXISTS (SELECT 1 FROM users WHERE users.genshin_uid = config.genshin_uid) \
+                                        AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE unlinked_users.genshin_uid = config.genshin_uid);")
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map::<String, _>(|row| row.get(0))
+        .collect::<Vec<String>>();
+
+    let duplicate_cookies = sqlx::query("SELECT ltuid, group_concat(cookie_id) FROM hoyo_cookie GROUP BY ltuid HAVING COUNT(*) > 1;")
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            let cookie_ids = row
+                .get::<String, _>(1)
+                .split(',')
+                .map(|cookie_id| cookie_id.parse::<u32>().unwrap())
+                .collect::<Vec<u32>>();
+
+            (row.get::<String, _>(0), cookie_ids)
+        })
+        .collect::<Vec<(String, Vec<u32>)>>();
+
+    let dangling_users = sqlx::query("SELECT discord_id, genshin_uid FROM users \
+                                      WHERE NOT EXISTS (SELECT 1 FROM config WHERE config.genshin_uid = users.genshin_uid) \
+                                      OR NOT EXISTS (SELECT 1 FROM hoyo_cookie WHERE cookie_id = hoyo_cookie_id);")
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.get::<i64, _>(0), row.get::<String, _>(1)))
+        .collect::<Vec<(i64, String)>>();
+
+    Report {
+        orphaned_cookies,
+        orphaned_configs,
+        duplicate_cookies,
+        dangling_users,
+    }
+}
+
+/// Fixes the issues of a report in one transaction. Duplicate cookies are merged into the newest
+/// one, and dangling links are removed.
+pub async fn repair(database: &sqlx::SqlitePool, report: &Report) -> Result<(), sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    for (ltuid, cookie_ids) in &report.duplicate_cookies {
+        let newest = cookie_ids.iter().max().unwrap();
+
+        for cookie_id in cookie_ids.iter().filter(|cookie_id| *cookie_id != newest) {
+            sqlx::query("UPDATE OR IGNORE users SET hoyo_cookie_id = ? WHERE hoyo_cookie_id = ?;")
+                .bind(newest)
+                .bind(cookie_id)
+                .execute(&mut tx)
+                .await?;
+
+            sqlx::query("DELETE FROM hoyo_cookie WHERE cookie_id = ? AND ltuid = ?;")
+                .bind(cookie_id)
+                .bind(ltuid)
+                .execute(&mut tx)
+                .await?;
+        }
+    }
+
+    for (discord_id, genshin_uid) in &report.dangling_users {
+        sqlx::query("DELETE FROM users WHERE (discord_id, genshin_uid) = (?, ?);")
+            .bind(discord_id)
+            .bind(genshin_uid)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    // Removing dangling links can orphan more rows, so these are looked up again
+    sqlx::query("DELETE FROM hoyo_cookie WHERE NOT EXISTS (SELECT 1 FROM users WHERE hoyo_cookie_id = cookie_id) \
+                 AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE hoyo_cookie_id = cookie_id);")
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query("DELETE FROM config WHERE NOT EXISTS (SELECT 1 FROM users WHERE users.genshin_uid = config.genshin_uid) \
+                 AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE unlinked_users.genshin_uid = config.genshin_uid);")
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await
+}
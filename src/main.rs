@@ -1,4 +1,16 @@
+mod alias;
+mod claim;
 mod commands;
+mod components;
+mod health;
+mod history;
+mod hoyolab;
+mod integrity;
+mod queue;
+mod session;
+mod trash;
+mod uid;
+mod wizard;
 
 use std::env;
 use std::sync::Arc;
@@ -16,9 +28,16 @@ struct Bot {
 #[async_trait]
 impl EventHandler for Bot {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(command) = interaction {
-            let ctx = Arc::new(ctx);
+        let ctx = Arc::new(ctx);
 
+        if let Interaction::MessageComponent(component) = interaction {
+            components::handle(&self.database, Arc::new(component), ctx.clone()).await;
+        } else if let Interaction::Autocomplete(autocomplete) = interaction {
+            // Every autocompleted option takes one of the user's accounts, claims need an owned one
+            let owned_only = autocomplete.data.name != "alias";
+
+            alias::autocomplete(&self.database, &autocomplete, &ctx, owned_only).await;
+        } else if let Interaction::ApplicationCommand(command) = interaction {
             match command.data.name.as_str() {
                 "claimcode" => {
                     commands::claim_code::run(&self.database, &command, ctx.clone()).await
@@ -26,9 +45,18 @@ impl EventHandler for Bot {
                 "claimdaily" => {
                     commands::claim_daily::run(&self.database, &command, ctx.clone()).await
                 }
+                "forgetme" => commands::forgetme::run(&self.database, &command, ctx.clone()).await,
+                "integrity" => {
+                    commands::integrity::run(&self.database, &command, ctx.clone()).await
+                }
+                "alias" => commands::alias::run(&self.database, &command, ctx.clone()).await,
                 "accounts" => commands::accounts::run(&self.database, &command, ctx.clone()).await,
                 "link" => commands::link::run(&self.database, &command, ctx.clone()).await,
+                "mydata" => commands::mydata::run(&self.database, &command, ctx.clone()).await,
+                "relink" => commands::relink::run(&self.database, &command, ctx.clone()).await,
+                "transfer" => commands::transfer::run(&self.database, &command, ctx.clone()).await,
                 "unlink" => commands::unlink::run(&self.database, &command, ctx.clone()).await,
+                "share" => commands::share::run(&self.database, &command, ctx.clone()).await,
                 "submitcode" => {
                     commands::submitcode::run(&self.database, &command, ctx.clone()).await
                 }
@@ -50,6 +78,19 @@ impl EventHandler for Bot {
         })
         .await
         .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| {
+            commands::forgetme::register(cmd)
+        })
+        .await
+        .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| {
+            commands::integrity::register(cmd)
+        })
+        .await
+        .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| commands::alias::register(cmd))
+            .await
+            .unwrap();
         Command::create_global_application_command(&ctx.http, |cmd| {
             commands::accounts::register(cmd)
         })
@@ -58,11 +99,29 @@ impl EventHandler for Bot {
         Command::create_global_application_command(&ctx.http, |cmd| commands::link::register(cmd))
             .await
             .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| {
+            commands::mydata::register(cmd)
+        })
+        .await
+        .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| {
+            commands::relink::register(cmd)
+        })
+        .await
+        .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| {
+            commands::transfer::register(cmd)
+        })
+        .await
+        .unwrap();
         Command::create_global_application_command(&ctx.http, |cmd| {
             commands::unlink::register(cmd)
         })
         .await
         .unwrap();
+        Command::create_global_application_command(&ctx.http, |cmd| commands::share::register(cmd))
+            .await
+            .unwrap();
         Command::create_global_application_command(&ctx.http, |cmd| {
             commands::submitcode::register(cmd)
         })
@@ -81,22 +140,42 @@ async fn main() {
         .connect_with(
             sqlx::sqlite::SqliteConnectOptions::new()
                 .filename("database.sqlite")
-                .create_if_missing(true),
+                .create_if_missing(true)
+                .foreign_keys(true),
         )
         .await
         .expect("Could not connect to database");
 
     sqlx::migrate!().run(&database).await.unwrap();
 
-    let bot = Bot { database };
+    let report = integrity::check(&database).await;
+
+    if !report.is_clean() {
+        println!(
+            "The database has integrity issues, run /integrity to repair them:\n{}",
+            report
+        );
+    }
+
+    let bot = Bot {
+        database: database.clone(),
+    };
 
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
 
     // Build our client.
     let mut client = Client::builder(token, GatewayIntents::DIRECT_MESSAGES)
         .event_handler(bot)
+        .type_map_insert::<session::Sessions>(Arc::new(session::Sessions::default()))
         .await
         .expect("Error creating client");
 
+    tokio::spawn(trash::run(database.clone()));
+    tokio::spawn(queue::run(
+        database.clone(),
+        client.cache_and_http.http.clone(),
+    ));
+    tokio::spawn(health::run(database, client.cache_and_http.http.clone()));
+
     client.start().await.expect("Error running bot.");
 }
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::http::Http;
+use sqlx::{Row, SqlitePool};
+
+use crate::commands::submitcode;
+use crate::hoyolab;
+
+/// Claims the codes that were put off while HoYoLab was down, once it is back.
+pub async fn run(database: SqlitePool, http: Arc<Http>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+
+        if hoyolab::is_available() {
+            drain(&database, &http).await;
+        }
+    }
+}
+
+async fn drain(database: &SqlitePool, http: &Http) {
+    let pending = sqlx::query("SELECT genshin_uid, code FROM pending_claims ORDER BY created_at;")
+        .fetch_all(database)
+        .await
+        .unwrap();
+
+    for claim in pending {
+        let genshin_uid: String = claim.get("genshin_uid");
+        let code: String = claim.get("code");
+
+        // The account may no longer be claimed on automatically, then the claim is dropped
+        let user = sqlx::query(&format!(
+            "{} AND users.genshin_uid = ?;",
+            submitcode::AUTO_CLAIM_USERS
+        ))
+        .bind(&genshin_uid)
+        .fetch_optional(database)
+        .await
+        .unwrap();
+
+        if let Some(user) = user {
+            // Still down, the claim stays queued
+            if !submitcode::auto_claim(database, http, &user, &code).await {
+                return;
+            }
+        }
+
+        sqlx::query("DELETE FROM pending_claims WHERE (genshin_uid, code) = (?, ?);")
+            .bind(&genshin_uid)
+            .bind(&code)
+            .execute(database)
+            .await
+            .unwrap();
+    }
+}
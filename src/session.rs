@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serenity::collector::modal_interaction_collector::CollectModalInteraction;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::{ChannelId, Message, UserId};
+use serenity::prelude::{Context, TypeMapKey};
+use tokio::sync::watch;
+
+/// How long a flow waits for the user to press a button or submit a form.
+pub const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The id and cancel signal of the flow each user has open, keyed by user and flow name.
+type Active = HashMap<(UserId, &'static str), (u64, watch::Sender<bool>)>;
+
+/// The flows users currently have open, keyed by user and flow name.
+pub struct Sessions {
+    first_id: u64,
+    next_id: AtomicU64,
+    active: Mutex<Active>,
+    /// The id and flow name of the session each user's uploads go to
+    uploads: Mutex<HashMap<UserId, (u64, &'static str)>>,
+}
+
+impl Default for Sessions {
+    /// Session ids continue from the current time, so they differ from those of earlier runs of
+    /// the bot.
+    fn default() -> Sessions {
+        let first_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        Sessions {
+            first_id,
+            next_id: AtomicU64::new(first_id),
+            active: Mutex::new(HashMap::new()),
+            uploads: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl TypeMapKey for Sessions {
+    type Value = Arc<Sessions>;
+}
+
+impl Sessions {
+    /// Starts a flow for the user, cancelling an earlier run of the same flow they left open.
+    pub async fn start(ctx: &Context, user_id: UserId, flow: &'static str) -> Session {
+        let sessions = ctx.data.read().await.get::<Sessions>().unwrap().clone();
+        let id = sessions.next_id.fetch_add(1, Ordering::Relaxed);
+        let (cancel, cancelled) = watch::channel(false);
+
+        let previous = sessions
+            .active
+            .lock()
+            .unwrap()
+            .insert((user_id, flow), (id, cancel));
+
+        if let Some((_, previous)) = previous {
+            previous.send(true).ok();
+        }
+
+        Session {
+            id,
+            user_id,
+            flow,
+            sessions,
+            cancelled,
+        }
+    }
+
+    /// Cancels a session, unless the user started its flow anew already.
+    fn cancel(&self, user_id: UserId, flow: &'static str, id: u64) {
+        if let Some((active_id, cancel)) = self.active.lock().unwrap().get(&(user_id, flow)) {
+            if *active_id == id {
+                cancel.send(true).ok();
+            }
+        }
+    }
+
+    /// Whether a custom id created by [`Session::custom_id`] belongs to a session started since
+    /// the bot started. Its collector handles it if the session is still waiting for input.
+    pub async fn started_here(ctx: &Context, custom_id: &str) -> bool {
+        let sessions = ctx.data.read().await.get::<Sessions>().unwrap().clone();
+
+        match split_custom_id(custom_id) {
+            Some((_, id, _)) => {
+                id >= sessions.first_id && id < sessions.next_id.load(Ordering::Relaxed)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Splits a custom id created by [`Session::custom_id`] into its flow, session id and action.
+pub fn split_custom_id(custom_id: &str) -> Option<(&str, u64, &str)> {
+    let mut parts = custom_id.splitn(3, ':');
+
+    let flow = parts.next()?;
+    let id = parts.next()?.parse::<u64>().ok()?;
+    let action = parts.next()?;
+
+    Some((flow, id, action))
+}
+
+/// A single run of a flow. Components and modals created through it carry its id in their
+/// `custom_id`, so their interactions only reach this run.
+pub struct Session {
+    id: u64,
+    user_id: UserId,
+    flow: &'static str,
+    sessions: Arc<Sessions>,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl Session {
+    /// Custom id for a component or modal of this session, e.g. `link:3:proceed`.
+    pub fn custom_id(&self, action: &str) -> String {
+        format!("{}:{}:{}", self.flow, self.id, action)
+    }
+
+    /// The action of a custom id created by [`Session::custom_id`].
+    pub fn action<'a>(&self, custom_id: &'a str) -> &'a str {
+        custom_id
+            .strip_prefix(&self.custom_id(""))
+            .unwrap_or(custom_id)
+    }
+
+    /// Whether the user started this flow anew, leaving this run behind.
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Waits for the user to use one of this session's components on `msg`.
+    pub async fn await_component(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+    ) -> Option<Arc<MessageComponentInteraction>> {
+        let prefix = self.custom_id("");
+
+        let collector = msg
+            .await_component_interaction(ctx)
+            .author_id(self.user_id)
+            .filter(move |interaction| interaction.data.custom_id.starts_with(&prefix))
+            .timeout(TIMEOUT);
+
+        self.until_cancelled(collector).await
+    }
+
+    /// Waits for the user to submit one of this session's modals.
+    pub async fn await_modal(&self, ctx: &Context) -> Option<Arc<ModalSubmitInteraction>> {
+        let prefix = self.custom_id("");
+
+        let collector = CollectModalInteraction::new(ctx)
+            .author_id(self.user_id)
+            .filter(move |submission| submission.data.custom_id.starts_with(&prefix))
+            .timeout(TIMEOUT);
+
+        self.until_cancelled(collector).await
+    }
+
+    /// Waits for the user to upload a file to `channel_id`. Uploads can not be told apart by flow,
+    /// so only one session per user waits for them, and the one that waited before is cancelled.
+    pub async fn await_upload(&self, ctx: &Context, channel_id: ChannelId) -> Option<Arc<Message>> {
+        let previous = self
+            .sessions
+            .uploads
+            .lock()
+            .unwrap()
+            .insert(self.user_id, (self.id, self.flow));
+
+        if let Some((id, flow)) = previous {
+            if id != self.id {
+                self.sessions.cancel(self.user_id, flow, id);
+            }
+        }
+
+        // Hands the uploads back once this session stops waiting, including when this future is
+        // dropped because the user pressed a button instead
+        let _waiting = UploadSlot(self);
+
+        let sessions = self.sessions.clone();
+        let (user_id, id) = (self.user_id, self.id);
+
+        let collector = channel_id
+            .await_reply(ctx)
+            .author_id(self.user_id)
+            .filter(move |reply| {
+                !reply.attachments.is_empty()
+                    && sessions
+                        .uploads
+                        .lock()
+                        .unwrap()
+                        .get(&user_id)
+                        .is_some_and(|(upload_id, _)| *upload_id == id)
+            })
+            .timeout(TIMEOUT);
+
+        self.until_cancelled(collector).await
+    }
+
+    /// Resolves to `None` instead of waiting on `collector` once the user starts this flow anew.
+    pub async fn until_cancelled<T>(
+        &self,
+        collector: impl Future<Output = Option<T>>,
+    ) -> Option<T> {
+        let mut cancelled = self.cancelled.clone();
+
+        tokio::select! {
+            result = collector => result,
+            _ = cancelled.changed() => None,
+        }
+    }
+}
+
+/// Marks a session as the one its user's uploads go to, for as long as it is held.
+struct UploadSlot<'a>(&'a Session);
+
+impl Drop for UploadSlot<'_> {
+    fn drop(&mut self) {
+        let mut uploads = self.0.sessions.uploads.lock().unwrap();
+
+        if uploads
+            .get(&self.0.user_id)
+            .is_some_and(|(id, _)| *id == self.0.id)
+        {
+            uploads.remove(&self.0.user_id);
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut active = self.sessions.active.lock().unwrap();
+
+        if let Some((id, _)) = active.get(&(self.user_id, self.flow)) {
+            if *id == self.id {
+                active.remove(&(self.user_id, self.flow));
+            }
+        }
+    }
+}
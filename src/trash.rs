@@ -0,0 +1,70 @@
+use std::env;
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+
+/// How long unlinked accounts can be restored, set through `UNLINK_GRACE_HOURS`.
+pub fn grace_period() -> Duration {
+    let hours = env::var("UNLINK_GRACE_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<u64>().ok())
+        .unwrap_or(24);
+
+    Duration::from_secs(hours * 60 * 60)
+}
+
+/// Hard-deletes unlinked accounts once their grace period is over, and unshares those left
+/// without an owner.
+pub async fn run(database: SqlitePool) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5 * 60));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = purge(&database).await {
+            println!("Error purging unlinked accounts:\n {}", error);
+        }
+    }
+}
+
+async fn purge(database: &SqlitePool) -> Result<(), sqlx::Error> {
+    let mut tx = database.begin().await?;
+
+    sqlx::query("DELETE FROM unlinked_users WHERE unlinked_at <= datetime('now', ?);")
+        .bind(format!("-{} seconds", grace_period().as_secs()))
+        .execute(&mut tx)
+        .await?;
+
+    // Accounts whose last owner can no longer undo their unlink are not shared anymore
+    for table in ["users", "unlinked_users"] {
+        sqlx::query(&format!(
+            "DELETE FROM {table} WHERE role = 'viewer' \
+             AND NOT EXISTS (SELECT 1 FROM users AS owner WHERE owner.genshin_uid = {table}.genshin_uid AND owner.role = 'owner') \
+             AND NOT EXISTS (SELECT 1 FROM unlinked_users AS owner WHERE owner.genshin_uid = {table}.genshin_uid AND owner.role = 'owner');",
+            table = table
+        ))
+        .execute(&mut tx)
+        .await?;
+    }
+
+    sqlx::query("DELETE FROM unlink_batches WHERE NOT EXISTS (SELECT 1 FROM unlinked_users WHERE unlinked_users.batch_id = unlink_batches.batch_id);")
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query("DELETE FROM config WHERE NOT EXISTS (SELECT 1 FROM users WHERE users.genshin_uid = config.genshin_uid) \
+                 AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE unlinked_users.genshin_uid = config.genshin_uid);")
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query("DELETE FROM claim_history WHERE NOT EXISTS (SELECT 1 FROM users WHERE users.genshin_uid = claim_history.genshin_uid) \
+                 AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE unlinked_users.genshin_uid = claim_history.genshin_uid);")
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query("DELETE FROM hoyo_cookie WHERE NOT EXISTS (SELECT 1 FROM users WHERE hoyo_cookie_id = cookie_id) \
+                 AND NOT EXISTS (SELECT 1 FROM unlinked_users WHERE hoyo_cookie_id = cookie_id);")
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await
+}
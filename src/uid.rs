@@ -0,0 +1,128 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    America,
+    Europe,
+    Asia,
+    TwHkMo,
+    China,
+}
+
+impl Region {
+    /// Identifier of the game server, as stored in the `config.region` column.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Region::America => "os_usa",
+            Region::Europe => "os_euro",
+            Region::Asia => "os_asia",
+            Region::TwHkMo => "os_cht",
+            Region::China => "cn_gf01",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::America => "America",
+            Region::Europe => "Europe",
+            Region::Asia => "Asia",
+            Region::TwHkMo => "TW, HK, MO",
+            Region::China => "China",
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UidError {
+    NotNumeric,
+    InvalidLength(usize),
+    /// A 10 digit UID that does not start with 1
+    InvalidPrefix(char),
+    UnknownRegion(char),
+}
+
+impl fmt::Display for UidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UidError::NotNumeric => write!(f, "a UID may only contain digits"),
+            UidError::InvalidLength(length) => write!(
+                f,
+                "a UID is 9 or 10 digits long, but this one has {}",
+                length
+            ),
+            UidError::InvalidPrefix(digit) => write!(
+                f,
+                "a 10 digit UID starts with 1, but this one starts with {}",
+                digit
+            ),
+            UidError::UnknownRegion(digit) => {
+                write!(f, "no server region starts with the digit {}", digit)
+            }
+        }
+    }
+}
+
+/// Validates a Genshin UID and derives the server region from it.
+///
+/// The region is encoded in the digit preceding the last eight digits, i.e. the
+/// first digit of a 9 digit UID or the second digit of a 10 digit UID.
+pub fn parse(uid: &str) -> Result<Region, UidError> {
+    if !uid.chars().all(|c| c.is_ascii_digit()) {
+        return Err(UidError::NotNumeric);
+    }
+
+    let region_digit = match uid.len() {
+        9 => uid.chars().next().unwrap(),
+        10 if uid.starts_with('1') => uid.chars().nth(1).unwrap(),
+        10 => return Err(UidError::InvalidPrefix(uid.chars().next().unwrap())),
+        length => return Err(UidError::InvalidLength(length)),
+    };
+
+    match region_digit {
+        '1' | '2' | '5' => Ok(Region::China),
+        '6' => Ok(Region::America),
+        '7' => Ok(Region::Europe),
+        '8' => Ok(Region::Asia),
+        '9' => Ok(Region::TwHkMo),
+        digit => Err(UidError::UnknownRegion(digit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nine_digit_uids() {
+        assert_eq!(parse("100000001"), Ok(Region::China));
+        assert_eq!(parse("500000001"), Ok(Region::China));
+        assert_eq!(parse("600000001"), Ok(Region::America));
+        assert_eq!(parse("700000001"), Ok(Region::Europe));
+        assert_eq!(parse("800000001"), Ok(Region::Asia));
+        assert_eq!(parse("900000001"), Ok(Region::TwHkMo));
+    }
+
+    #[test]
+    fn parses_ten_digit_uids_by_their_second_digit() {
+        assert_eq!(parse("1600000001"), Ok(Region::America));
+        assert_eq!(parse("1800000001"), Ok(Region::Asia));
+    }
+
+    #[test]
+    fn rejects_invalid_uids() {
+        assert_eq!(parse("80000000a"), Err(UidError::NotNumeric));
+        assert_eq!(parse(" 800000001"), Err(UidError::NotNumeric));
+        assert_eq!(parse(""), Err(UidError::InvalidLength(0)));
+        assert_eq!(parse("80000001"), Err(UidError::InvalidLength(8)));
+        assert_eq!(parse("2800000001"), Err(UidError::InvalidPrefix('2')));
+        assert_eq!(parse("18000000010"), Err(UidError::InvalidLength(11)));
+        assert_eq!(parse("300000001"), Err(UidError::UnknownRegion('3')));
+        assert_eq!(parse("1000000001"), Err(UidError::UnknownRegion('0')));
+    }
+}
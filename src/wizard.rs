@@ -0,0 +1,440 @@
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::builder::{CreateComponents, CreateEmbed, CreateSelectMenuOption};
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::component::{ActionRowComponent, ButtonStyle, InputTextStyle};
+use serenity::model::prelude::interaction::InteractionResponseType;
+use serenity::model::prelude::{Message, User};
+use serenity::prelude::Context;
+
+use crate::session::{Session, Sessions};
+
+/// What a step wants the wizard to do next.
+pub enum Transition<S> {
+    /// Go on to the given step, which can go back to the current one.
+    Next(S),
+    /// Run the given step in place of the current one, e.g. to ask again after invalid input.
+    Stay(S),
+    Done,
+}
+
+/// Why a step ended without the user answering it.
+#[derive(Debug)]
+pub enum Stop {
+    Back,
+    Cancel,
+    TimedOut,
+}
+
+/// A multi-step conversation, such as linking an account. Each step shows the user a [`Page`]
+/// and decides on the next step based on the answer.
+#[async_trait]
+pub trait Flow: Send {
+    type Step: Clone + Send;
+
+    async fn step(
+        &mut self,
+        wizard: &mut Wizard,
+        step: Self::Step,
+    ) -> Result<Transition<Self::Step>, Stop>;
+}
+
+/// A message with buttons and an optional select menu. Cancel and back buttons are added by the
+/// wizard.
+#[derive(Default)]
+pub struct Page {
+    embed: Option<CreateEmbed>,
+    buttons: Vec<(String, String, ButtonStyle)>,
+    persistent_buttons: Vec<(String, String, ButtonStyle)>,
+    select: Option<(String, Vec<CreateSelectMenuOption>, u64)>,
+}
+
+impl Page {
+    pub fn new(description: impl ToString) -> Page {
+        let mut embed = CreateEmbed::default();
+        embed.description(description).colour((122, 71, 72));
+
+        Page {
+            embed: Some(embed),
+            ..Default::default()
+        }
+    }
+
+    pub fn button(mut self, action: &str, label: &str, style: ButtonStyle) -> Page {
+        self.buttons
+            .push((action.to_string(), label.to_string(), style));
+        self
+    }
+
+    /// Adds a button that is handled by `components::handle` rather than the wizard, so it keeps
+    /// working after the flow ends.
+    pub fn persistent_button(mut self, custom_id: &str, label: &str, style: ButtonStyle) -> Page {
+        self.persistent_buttons
+            .push((custom_id.to_string(), label.to_string(), style));
+        self
+    }
+
+    /// Adds a select menu, of which the user may pick up to `max_values` options.
+    pub fn select(
+        mut self,
+        placeholder: &str,
+        options: Vec<CreateSelectMenuOption>,
+        max_values: u64,
+    ) -> Page {
+        self.select = Some((placeholder.to_string(), options, max_values));
+        self
+    }
+}
+
+/// The user's answer to a page: the action of the button they pressed, or the values they
+/// selected.
+pub struct Input {
+    pub action: String,
+    pub values: Vec<String>,
+}
+
+/// The user's answer to a page that also accepts a file upload.
+pub enum Answer {
+    /// They pressed a button instead of uploading a file
+    Input,
+    Upload(Arc<Message>),
+}
+
+/// Where the next message is shown. An interaction has to be answered, so after the user pressed a
+/// button or submitted a form the next message is sent as its response.
+enum Reply {
+    Direct,
+    Component(Arc<MessageComponentInteraction>),
+    Modal(Arc<ModalSubmitInteraction>),
+}
+
+pub struct Wizard {
+    ctx: Arc<Context>,
+    session: Session,
+    user: User,
+    reply: Reply,
+    can_go_back: bool,
+}
+
+impl Wizard {
+    /// Starts a wizard in the user's DMs, answering `interaction` with the first page if given.
+    pub async fn start(
+        ctx: Arc<Context>,
+        flow: &'static str,
+        user: &User,
+        interaction: Option<Arc<MessageComponentInteraction>>,
+    ) -> Wizard {
+        let session = Sessions::start(&ctx, user.id, flow).await;
+
+        Wizard {
+            ctx,
+            session,
+            user: user.clone(),
+            reply: match interaction {
+                Some(interaction) => Reply::Component(interaction),
+                None => Reply::Direct,
+            },
+            can_go_back: false,
+        }
+    }
+
+    pub async fn run<F: Flow>(mut self, flow: &mut F, first: F::Step) {
+        let mut history = Vec::new();
+        let mut current = first;
+
+        loop {
+            self.can_go_back = !history.is_empty();
+
+            match flow.step(&mut self, current.clone()).await {
+                Ok(Transition::Next(next)) => {
+                    history.push(current);
+                    current = next;
+                }
+                Ok(Transition::Stay(step)) => current = step,
+                Ok(Transition::Done) => return,
+                Err(Stop::Back) => match history.pop() {
+                    Some(previous) => current = previous,
+                    None => return,
+                },
+                Err(Stop::Cancel) | Err(Stop::TimedOut) => return,
+            }
+        }
+    }
+
+    /// Shows a page and waits for the user to answer it.
+    pub async fn ask(&mut self, page: Page) -> Result<Input, Stop> {
+        let msg = self.show(&page).await;
+        let interaction = self.session.await_component(&self.ctx, &msg).await;
+
+        self.answer(msg, &page, interaction).await
+    }
+
+    /// Shows a page and waits for the user to either answer it, or upload a file instead.
+    pub async fn ask_with_upload(&mut self, page: Page) -> Result<Answer, Stop> {
+        let msg = self.show(&page).await;
+
+        let result = tokio::select! {
+            interaction = self.session.await_component(&self.ctx, &msg) => Ok(interaction),
+            upload = self.session.await_upload(&self.ctx, msg.channel_id) => Err(upload),
+        };
+
+        match result {
+            Ok(interaction) => self
+                .answer(msg, &page, interaction)
+                .await
+                .map(|_| Answer::Input),
+            Err(Some(upload)) => {
+                msg.delete(&self.ctx).await.unwrap();
+                self.reply = Reply::Direct;
+
+                Ok(Answer::Upload(upload))
+            }
+            Err(None) => self.answer(msg, &page, None).await.map(|_| Answer::Input),
+        }
+    }
+
+    /// Opens a form in response to the button the user just pressed, with a text input for every
+    /// label, and returns what the user entered.
+    pub async fn form(
+        &mut self,
+        title: &str,
+        inputs: &[(&str, InputTextStyle)],
+    ) -> Result<Vec<String>, Stop> {
+        let interaction = match std::mem::replace(&mut self.reply, Reply::Direct) {
+            Reply::Component(interaction) => interaction,
+            _ => panic!("A form can only be opened in response to a component"),
+        };
+
+        interaction
+            .create_interaction_response(&self.ctx, |res| {
+                res.kind(InteractionResponseType::Modal)
+                    .interaction_response_data(|msg| {
+                        msg.title(title)
+                            .custom_id(self.session.custom_id("form"))
+                            .components(|comp| {
+                                for (index, (label, style)) in inputs.iter().enumerate() {
+                                    comp.create_action_row(|row| {
+                                        row.create_input_text(|input| {
+                                            input.custom_id(index).label(label).style(*style)
+                                        })
+                                    });
+                                }
+
+                                comp
+                            })
+                    })
+            })
+            .await
+            .unwrap();
+
+        let submission = match self.session.await_modal(&self.ctx).await {
+            Some(submission) => submission,
+            None => {
+                self.say("The form timed out.").await;
+                return Err(Stop::TimedOut);
+            }
+        };
+
+        let values = submission
+            .data
+            .components
+            .iter()
+            .map(|row| match row.components.first() {
+                Some(ActionRowComponent::InputText(input)) => input.value.clone(),
+                _ => String::new(),
+            })
+            .collect();
+
+        self.reply = Reply::Modal(submission);
+
+        Ok(values)
+    }
+
+    /// Shows a message without components, e.g. the outcome of the flow.
+    pub async fn say(&mut self, content: impl ToString) {
+        self.send(content.to_string(), &Page::default(), false)
+            .await;
+    }
+
+    /// Shows the last page of the flow, which is not waited on.
+    pub async fn finish(&mut self, page: Page) {
+        self.send(String::new(), &page, false).await;
+    }
+
+    async fn show(&mut self, page: &Page) -> Message {
+        self.send(String::new(), page, true).await
+    }
+
+    async fn send(&mut self, content: String, page: &Page, controls: bool) -> Message {
+        let components = self.components(page, controls, false);
+        let embeds = page.embed.clone().into_iter().collect::<Vec<CreateEmbed>>();
+
+        match std::mem::replace(&mut self.reply, Reply::Direct) {
+            Reply::Direct => self
+                .user
+                .direct_message(&self.ctx, |msg| {
+                    msg.content(content)
+                        .set_embeds(embeds)
+                        .set_components(components)
+                })
+                .await
+                .unwrap(),
+            Reply::Component(interaction) => {
+                interaction
+                    .create_interaction_response(&self.ctx, |res| {
+                        res.kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|msg| {
+                                msg.content(content)
+                                    .set_embeds(embeds)
+                                    .set_components(components)
+                            })
+                    })
+                    .await
+                    .unwrap();
+
+                interaction
+                    .get_interaction_response(&self.ctx.http)
+                    .await
+                    .unwrap()
+            }
+            Reply::Modal(submission) => {
+                submission
+                    .create_interaction_response(&self.ctx, |res| {
+                        res.kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|msg| {
+                                msg.content(content)
+                                    .set_embeds(embeds)
+                                    .set_components(components)
+                            })
+                    })
+                    .await
+                    .unwrap();
+
+                submission
+                    .get_interaction_response(&self.ctx.http)
+                    .await
+                    .unwrap()
+            }
+        }
+    }
+
+    fn components(&self, page: &Page, controls: bool, disabled: bool) -> CreateComponents {
+        let mut components = CreateComponents::default();
+
+        if let Some((placeholder, options, max_values)) = &page.select {
+            components.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu.custom_id(self.session.custom_id("select"))
+                        .placeholder(placeholder)
+                        .min_values(1)
+                        .max_values(*max_values)
+                        .disabled(disabled)
+                        .options(|select| select.set_options(options.clone()))
+                })
+            });
+        }
+
+        if controls || !page.persistent_buttons.is_empty() {
+            components.create_action_row(|row| {
+                if controls {
+                    row.create_button(|btn| {
+                        btn.custom_id(self.session.custom_id("cancel"))
+                            .label("Cancel")
+                            .style(ButtonStyle::Secondary)
+                            .disabled(disabled)
+                    });
+                }
+
+                if controls && self.can_go_back {
+                    row.create_button(|btn| {
+                        btn.custom_id(self.session.custom_id("back"))
+                            .label("Back")
+                            .style(ButtonStyle::Secondary)
+                            .disabled(disabled)
+                    });
+                }
+
+                for (action, label, style) in &page.buttons {
+                    row.create_button(|btn| {
+                        btn.custom_id(self.session.custom_id(action))
+                            .label(label)
+                            .style(*style)
+                            .disabled(disabled)
+                    });
+                }
+
+                for (custom_id, label, style) in &page.persistent_buttons {
+                    row.create_button(|btn| btn.custom_id(custom_id).label(label).style(*style));
+                }
+
+                row
+            });
+        }
+
+        components
+    }
+
+    async fn answer(
+        &mut self,
+        msg: Message,
+        page: &Page,
+        interaction: Option<Arc<MessageComponentInteraction>>,
+    ) -> Result<Input, Stop> {
+        let interaction = match interaction {
+            Some(interaction) => interaction,
+            None => {
+                self.expire(msg, page).await;
+                return Err(Stop::TimedOut);
+            }
+        };
+
+        let input = Input {
+            action: self.session.action(&interaction.data.custom_id).to_string(),
+            values: interaction.data.values.clone(),
+        };
+
+        // The page is replaced in place, so no prompt is left behind
+        if input.action == "cancel" {
+            interaction
+                .create_interaction_response(&self.ctx, |res| {
+                    res.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|msg| {
+                            msg.content("Cancelled.")
+                                .set_embeds(Vec::new())
+                                .set_components(CreateComponents::default())
+                        })
+                })
+                .await
+                .unwrap();
+
+            return Err(Stop::Cancel);
+        }
+
+        msg.delete(&self.ctx).await.unwrap();
+        self.reply = Reply::Component(interaction);
+
+        match input.action.as_str() {
+            "back" => Err(Stop::Back),
+            _ => Ok(input),
+        }
+    }
+
+    /// Leaves an unanswered page on screen with its components disabled.
+    async fn expire(&self, mut msg: Message, page: &Page) {
+        let content = if self.session.is_cancelled() {
+            "This menu was replaced by a newer one."
+        } else {
+            "This menu timed out."
+        };
+
+        let components = self.components(page, true, true);
+
+        msg.edit(&self.ctx, |edit| {
+            edit.content(content).set_components(components)
+        })
+        .await
+        .unwrap();
+    }
+}